@@ -0,0 +1,128 @@
+//! Code to measure the temperature using the internal temperature sensor and the ADC.
+
+
+use atsaml21g18b::Peripherals;
+
+
+/// The output voltage of the temperature sensor at 25 °C, in microvolts.
+///
+/// Typical value from the SAM L21 datasheet; individual parts may deviate by a few degrees.
+const TEMPERATURE_SENSOR_UV_AT_25C: i32 = 667_000;
+
+/// The change of the temperature sensor's output voltage per degree Celsius, in microvolts.
+///
+/// Typical value from the SAM L21 datasheet.
+const TEMPERATURE_SENSOR_UV_PER_DEGREE: i32 = 2_400;
+
+/// The voltage of the internal reference used for the conversion, in microvolts.
+const REFERENCE_UV: i32 = 1_000_000;
+
+const MUXPOS_TEMPERATURE_SENSOR: u8 = 0x18;
+const MUXNEG_GROUND: u8 = 0x18;
+const REFSEL_INTERNAL: u8 = 0x0;
+const SUPC_VREF_SEL_1V0: u8 = 0x0;
+
+// the ADC clock is the core clock divided by 4 and may not exceed 16 MHz
+const _: () = assert!(crate::init::CORE_CLOCK_SPEED_HZ / 4 <= 16_000_000, "core clock too fast for the ADC");
+
+
+/// Converts a 12-bit ADC result of the temperature sensor into thousandths of a degree Celsius.
+const fn result_to_millicelsius(result: u16) -> i32 {
+    let microvolts = ((result as i64) * (REFERENCE_UV as i64) / 4096) as i32;
+    25_000 + (microvolts - TEMPERATURE_SENSOR_UV_AT_25C) * 1000 / TEMPERATURE_SENSOR_UV_PER_DEGREE
+}
+
+
+/// Sets up the ADC to measure the internal temperature sensor.
+///
+/// The ADC must be connected to the core clock before calling this function.
+pub(crate) fn setup_adc(peripherals: &mut Peripherals) {
+    // enable CLK_ADC_APB
+    peripherals.MCLK.apbdmask.modify(|_, w| w
+        .adc_().set_bit()
+    );
+
+    // enable the temperature sensor and select the 1.0V reference
+    peripherals.SUPC.vref.modify(|_, w| unsafe { w
+        .tsen().set_bit()
+        .sel().bits(SUPC_VREF_SEL_1V0)
+    });
+
+    let register_block = &peripherals.ADC;
+
+    // reset ADC
+    register_block.ctrla.modify(|_, w| w
+        .swrst().set_bit()
+    );
+    while register_block.ctrla.read().swrst().bit_is_set() || register_block.syncbusy.read().swrst().bit_is_set() {
+    }
+
+    // load calibration values
+    let calibration = crate::init::calibration();
+    register_block.calib.write(|w| unsafe { w
+        .biasrefbuf().bits(calibration.adc_linearity)
+        .biascomp().bits(calibration.adc_bias)
+    });
+
+    // basic configuration
+    register_block.ctrlb.modify(|_, w| w
+        .prescaler().div4() // core clock / 4 (maximum is 16 MHz; see above)
+    );
+    register_block.refctrl.modify(|_, w| unsafe { w
+        .refsel().bits(REFSEL_INTERNAL) // internal reference (1.0V, see above)
+        .refcomp().clear_bit() // no reference buffer offset compensation
+    });
+    // REFCTRL is enable-protected but not synchronized; there is no SYNCBUSY bit to wait for
+    register_block.inputctrl.modify(|_, w| unsafe { w
+        .muxpos().bits(MUXPOS_TEMPERATURE_SENSOR)
+        .muxneg().bits(MUXNEG_GROUND)
+    });
+    while register_block.syncbusy.read().inputctrl().bit_is_set() {
+    }
+    register_block.ctrlc.modify(|_, w| w
+        .diffmode().clear_bit() // single-ended
+        .leftadj().clear_bit() // right-adjusted result
+        .freerun().clear_bit() // single conversion on trigger
+        .corren().clear_bit() // no digital correction
+        .ressel()._12bit()
+    );
+    while register_block.syncbusy.read().ctrlc().bit_is_set() {
+    }
+    register_block.sampctrl.modify(|_, w| w
+        .samplen().variant(63) // longest sampling time; the temperature sensor has a high impedance
+    );
+    while register_block.syncbusy.read().sampctrl().bit_is_set() {
+    }
+
+    // enable ADC
+    register_block.ctrla.modify(|_, w| w
+        .enable().set_bit()
+    );
+    while register_block.syncbusy.read().enable().bit_is_set() {
+    }
+}
+
+
+/// Measures the temperature, returning it in thousandths of a degree Celsius.
+pub(crate) fn read_temperature_millicelsius(peripherals: &mut Peripherals) -> i32 {
+    let register_block = &peripherals.ADC;
+
+    // start the conversion
+    register_block.swtrig.write(|w| w
+        .start().set_bit()
+    );
+    while register_block.syncbusy.read().swtrig().bit_is_set() {
+    }
+
+    // wait for the result, then clear that bit
+    while register_block.intflag.read().resrdy().bit_is_clear() {
+    }
+    unsafe {
+        register_block.intflag.write_with_zero(|w| w
+            .resrdy().set_bit()
+        )
+    };
+
+    let result = register_block.result.read().result().bits();
+    result_to_millicelsius(result)
+}
@@ -0,0 +1,60 @@
+//! Switching the display backlight off during the night.
+//!
+//! The night is given by a start and an end hour (see [`crate::config::Config`]) and may span
+//! midnight. The backlight can be toggled manually; this override holds until the next time the
+//! night starts or ends, after which the schedule takes over again.
+
+
+/// Decides whether the display backlight should be on.
+pub(crate) struct BacklightSchedule {
+    day_backlight: bool,
+    night_start_hour: u8,
+    night_end_hour: u8,
+    was_night: Option<bool>,
+    manual_backlight: Option<bool>,
+}
+impl BacklightSchedule {
+    /// Creates a new schedule.
+    ///
+    /// `day_backlight` is whether the backlight is on outside the night. If `night_start_hour` and
+    /// `night_end_hour` are equal, there is no night.
+    pub const fn new(day_backlight: bool, night_start_hour: u8, night_end_hour: u8) -> Self {
+        Self {
+            day_backlight,
+            night_start_hour,
+            night_end_hour,
+            was_night: None,
+            manual_backlight: None,
+        }
+    }
+
+    /// Whether the given hour (0 to 23) is part of the night.
+    pub const fn is_night(&self, hour: u8) -> bool {
+        if self.night_start_hour <= self.night_end_hour {
+            hour >= self.night_start_hour && hour < self.night_end_hour
+        } else {
+            // spans midnight
+            hour >= self.night_start_hour || hour < self.night_end_hour
+        }
+    }
+
+    /// Returns whether the backlight should be on at the given hour (0 to 23).
+    ///
+    /// A manual override is dropped if the night has started or ended since the previous call.
+    pub fn backlight_at(&mut self, hour: u8) -> bool {
+        let night = self.is_night(hour);
+        if self.was_night != Some(night) {
+            self.was_night = Some(night);
+            self.manual_backlight = None;
+        }
+        self.manual_backlight
+            .unwrap_or(self.day_backlight && !night)
+    }
+
+    /// Manually switches the backlight from the given state to the other one, returning the new
+    /// state.
+    pub fn toggle(&mut self, backlight: bool) -> bool {
+        self.manual_backlight = Some(!backlight);
+        !backlight
+    }
+}
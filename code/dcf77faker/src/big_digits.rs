@@ -0,0 +1,197 @@
+//! Rendering of numbers as big digits, two rows tall, using custom characters.
+//!
+//! Each digit is three columns wide and two rows tall. It is composed of the display's built-in
+//! full block and three custom characters containing a bar at the top, a bar at the bottom, or
+//! both; together, they can represent the segments of a seven-segment digit.
+
+
+use crate::i2c_controller::{I2cError, SercomI2cController};
+use crate::i2c_display::{CustomChar, I2cDisplay};
+
+use atsaml21g18b::Peripherals;
+
+
+/// The code of the custom character with the bar at the top.
+const UPPER_BAR: u8 = CustomChar::ALL[0].code();
+
+/// The code of the custom character with the bar at the bottom.
+const LOWER_BAR: u8 = CustomChar::ALL[1].code();
+
+/// The code of the custom character with the bars at the top and the bottom.
+const BOTH_BARS: u8 = CustomChar::ALL[2].code();
+
+/// The full block in the HD44780 character ROM A00.
+const FULL_BLOCK: u8 = 0xFF;
+
+const BLANK: u8 = b' ';
+
+/// A dot in the middle of the character cell (katakana middle dot) in the HD44780 character ROM A00;
+/// one on each row forms the colon between big digits.
+const MIDDLE_DOT: u8 = 0xA5;
+
+/// The dot patterns of the custom characters, in the order of [`CustomChar::ALL`].
+const GLYPHS: [[u8; 8]; 3] = [
+    [0b11111, 0b11111, 0b11111, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+    [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111, 0b11111, 0b11111],
+    [0b11111, 0b11111, 0b11111, 0b00000, 0b00000, 0b11111, 0b11111, 0b11111],
+];
+const _: () = assert!(GLYPHS.len() <= CustomChar::ALL.len(), "more glyphs than custom characters");
+
+/// The number of columns taken up by a big digit, including the gap to the next digit.
+pub(crate) const BIG_DIGIT_COLUMNS: u8 = 4;
+
+/// A value for [`draw_big_digits`](BigDigits::draw_big_digits) that leaves the place of a digit
+/// blank.
+const NO_DIGIT: u8 = 10;
+
+/// The column of the colon between the hour and the minute in the output of
+/// [`draw_big_time`](BigDigits::draw_big_time).
+const BIG_HOUR_MINUTE_SEPARATOR_COLUMN: u8 = 2 * BIG_DIGIT_COLUMNS;
+
+/// The column of the minute in the output of [`draw_big_time`](BigDigits::draw_big_time).
+const BIG_MINUTE_COLUMN: u8 = BIG_HOUR_MINUTE_SEPARATOR_COLUMN + 1;
+
+/// The column of the second in the output of [`draw_big_time`](BigDigits::draw_big_time).
+const BIG_SECOND_COLUMN: u8 = BIG_MINUTE_COLUMN + 2 * BIG_DIGIT_COLUMNS;
+
+const SEGMENT_A: u8 = 1 << 0; // top
+const SEGMENT_B: u8 = 1 << 1; // top right
+const SEGMENT_C: u8 = 1 << 2; // bottom right
+const SEGMENT_D: u8 = 1 << 3; // bottom
+const SEGMENT_E: u8 = 1 << 4; // bottom left
+const SEGMENT_F: u8 = 1 << 5; // top left
+const SEGMENT_G: u8 = 1 << 6; // middle
+
+/// The seven-segment representation of each decimal digit.
+const DIGIT_SEGMENTS: [u8; 10] = [
+    SEGMENT_A | SEGMENT_B | SEGMENT_C | SEGMENT_D | SEGMENT_E | SEGMENT_F,
+    SEGMENT_B | SEGMENT_C,
+    SEGMENT_A | SEGMENT_B | SEGMENT_D | SEGMENT_E | SEGMENT_G,
+    SEGMENT_A | SEGMENT_B | SEGMENT_C | SEGMENT_D | SEGMENT_G,
+    SEGMENT_B | SEGMENT_C | SEGMENT_F | SEGMENT_G,
+    SEGMENT_A | SEGMENT_C | SEGMENT_D | SEGMENT_F | SEGMENT_G,
+    SEGMENT_A | SEGMENT_C | SEGMENT_D | SEGMENT_E | SEGMENT_F | SEGMENT_G,
+    SEGMENT_A | SEGMENT_B | SEGMENT_C,
+    SEGMENT_A | SEGMENT_B | SEGMENT_C | SEGMENT_D | SEGMENT_E | SEGMENT_F | SEGMENT_G,
+    SEGMENT_A | SEGMENT_B | SEGMENT_C | SEGMENT_D | SEGMENT_F | SEGMENT_G,
+];
+
+
+/// Selects the character showing the given horizontal bars.
+const fn bars(upper: bool, lower: bool) -> u8 {
+    match (upper, lower) {
+        (false, false) => BLANK,
+        (true, false) => UPPER_BAR,
+        (false, true) => LOWER_BAR,
+        (true, true) => BOTH_BARS,
+    }
+}
+
+
+/// Returns the characters of the upper and lower row of the given big digit, or `None` if it is not
+/// a decimal digit (0 through 9).
+///
+/// In the upper row, the middle segment is drawn as a bar at the bottom; in the lower row, the
+/// bottom segment is.
+fn big_digit(digit: u8) -> Option<([u8; 3], [u8; 3])> {
+    let segments = *DIGIT_SEGMENTS.get(digit as usize)?;
+    let has = |segment: u8| segments & segment != 0;
+
+    let upper_bars = bars(has(SEGMENT_A), has(SEGMENT_G));
+    let lower_bars = bars(false, has(SEGMENT_D));
+    let upper = [
+        if has(SEGMENT_F) { FULL_BLOCK } else { upper_bars },
+        upper_bars,
+        if has(SEGMENT_B) { FULL_BLOCK } else { upper_bars },
+    ];
+    let lower = [
+        if has(SEGMENT_E) { FULL_BLOCK } else { lower_bars },
+        lower_bars,
+        if has(SEGMENT_C) { FULL_BLOCK } else { lower_bars },
+    ];
+    Some((upper, lower))
+}
+
+
+/// Big-digit output for I2C displays.
+pub(crate) trait BigDigits<T: SercomI2cController>: I2cDisplay<T> {
+    /// Defines the custom characters required for big digits.
+    ///
+    /// This must be called after [`basic_setup`](I2cDisplay::basic_setup) and before drawing big
+    /// digits. It occupies the custom characters 0 through 2.
+    fn define_big_digit_glyphs(&self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
+        for (character, glyph) in CustomChar::ALL.into_iter().zip(GLYPHS.iter()) {
+            self.define_custom_char(peripherals, character, glyph)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the given decimal digits (0 through 9 each) as big digits, with the top left corner at
+    /// the given column and row.
+    ///
+    /// Each digit takes up [`BIG_DIGIT_COLUMNS`] columns on the given row and the one below it. A
+    /// value above 9 leaves its columns blank.
+    fn draw_big_digits(&self, peripherals: &mut Peripherals, digits: &[u8], row: u8, column: u8) -> Result<(), I2cError> {
+        for (i, digit) in digits.iter().enumerate() {
+            let (upper, lower) = big_digit(*digit)
+                .unwrap_or(([BLANK; 3], [BLANK; 3]));
+            let digit_column = column + (i as u8) * BIG_DIGIT_COLUMNS;
+
+            self.set_cursor(peripherals, digit_column, row)?;
+            self.write_text(peripherals, upper.iter().copied().chain(Some(BLANK)))?;
+            self.set_cursor(peripherals, digit_column, row + 1)?;
+            self.write_text(peripherals, lower.iter().copied().chain(Some(BLANK)))?;
+        }
+        Ok(())
+    }
+
+    /// Draws the given number as big digits, with the top left corner at the given column and row.
+    ///
+    /// The number is drawn without leading zeros; use [`draw_big_digits`](BigDigits::draw_big_digits)
+    /// for a fixed number of digits.
+    fn draw_big_number(&self, peripherals: &mut Peripherals, value: u32, row: u8, column: u8) -> Result<(), I2cError> {
+        let mut digits = [0u8; 10];
+        let mut start = digits.len();
+        let mut rest = value;
+        loop {
+            start -= 1;
+            digits[start] = (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        self.draw_big_digits(peripherals, &digits[start..], row, column)
+    }
+
+    /// Draws the hour and the minute as big digits at the start of the given row and the one below
+    /// it, followed by the second in regular digits on the lower row.
+    ///
+    /// Like on most clocks, the hour is shown without a leading zero.
+    fn draw_big_time(&self, peripherals: &mut Peripherals, row: u8, hour: u8, minute: u8, second: u8) -> Result<(), I2cError> {
+        let hour_column = if hour < 10 {
+            self.draw_big_digits(peripherals, &[NO_DIGIT], row, 0)?;
+            BIG_DIGIT_COLUMNS
+        } else {
+            0
+        };
+        self.draw_big_number(peripherals, hour.into(), row, hour_column)?;
+        self.show_big_hour_minute_separator(peripherals, row, true)?;
+        self.draw_big_digits(peripherals, &[minute / 10, minute % 10], row, BIG_MINUTE_COLUMN)?;
+
+        self.set_cursor(peripherals, BIG_SECOND_COLUMN, row + 1)?;
+        self.write_text(peripherals, [b'0' + second / 10, b'0' + second % 10])
+    }
+
+    /// Shows or hides the colon between the hour and the minute that
+    /// [`draw_big_time`](BigDigits::draw_big_time) output on the given row, e.g. to make it blink.
+    fn show_big_hour_minute_separator(&self, peripherals: &mut Peripherals, row: u8, visible: bool) -> Result<(), I2cError> {
+        let character = if visible { MIDDLE_DOT } else { BLANK };
+        self.set_cursor(peripherals, BIG_HOUR_MINUTE_SEPARATOR_COLUMN, row)?;
+        self.write_text(peripherals, [character])?;
+        self.set_cursor(peripherals, BIG_HOUR_MINUTE_SEPARATOR_COLUMN, row + 1)?;
+        self.write_text(peripherals, [character])
+    }
+}
+impl<T: SercomI2cController, D: I2cDisplay<T>> BigDigits<T> for D {
+}
@@ -7,51 +7,149 @@ fn read_calibration_area() -> u32 {
     unsafe { *calibration_area_ptr }
 }
 
+/// Extracts the field of the given width starting at the given bit from the calibration area.
+pub const fn extract_field(calibration_area: u32, lowest_bit: u32, bit_count: u32) -> u8 {
+    ((calibration_area >> lowest_bit) & ((1 << bit_count) - 1)) as u8
+}
+
+
+/// All calibration values stored in the NVM software calibration area.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CalibrationData {
+    /// The ADC linearity calibration value. (bits 2:0)
+    ///
+    /// To be stored into `ADC.calib.biasrefbuf`.
+    pub adc_linearity: u8,
+
+    /// The ADC bias calibration value. (bits 5:3)
+    ///
+    /// To be stored into `ADC.calib.biascomp`.
+    pub adc_bias: u8,
+
+    /// The 32kHz internal oscillator calibration value. (bits 12:6)
+    ///
+    /// To be stored into `OSC32KCTRL.osc32k.calib`.
+    pub osc32k: u8,
+
+    /// The USB TRANSN calibration value. (bits 17:13)
+    ///
+    /// To be stored into `USB.$mode().padcal.transn`.
+    pub usb_transn: u8,
+
+    /// The USB TRANSP calibration value. (bits 22:18)
+    ///
+    /// To be stored into `USB.$mode().padcal.transp`.
+    pub usb_transp: u8,
+
+    /// The USB TRIM calibration value. (bits 25:23)
+    ///
+    /// To be stored into `USB.$mode().padcal.trim`.
+    pub usb_trim: u8,
+
+    /// The DFLL48M coarse calibration value. (bits 31:26)
+    ///
+    /// To be stored into `OSCCTRL.dfllval.coarse`.
+    pub dfll48m_coarse: u8,
+}
+impl CalibrationData {
+    /// Splits the contents of the calibration area into the individual values.
+    pub const fn from_area(calibration_area: u32) -> Self {
+        Self {
+            adc_linearity: extract_field(calibration_area, 0, 3),
+            adc_bias: extract_field(calibration_area, 3, 3),
+            osc32k: extract_field(calibration_area, 6, 7),
+            usb_transn: extract_field(calibration_area, 13, 5),
+            usb_transp: extract_field(calibration_area, 18, 5),
+            usb_trim: extract_field(calibration_area, 23, 3),
+            dfll48m_coarse: extract_field(calibration_area, 26, 6),
+        }
+    }
+}
+
+/// Reads all calibration values from NVM at once.
+///
+/// The flash wait states must be configured for the current core clock before calling this
+/// function (or any of the functions for individual values), as the calibration area is read from
+/// flash. The firmware reads the values once during initialization and keeps them around.
+pub fn read_calibration() -> CalibrationData {
+    CalibrationData::from_area(read_calibration_area())
+}
+
 /// The ADC linearity calibration value.
 ///
 /// Bits 2:0; to be stored into `ADC.calib.biasrefbuf`.
-pub(crate) fn adc_linearity() -> u8 {
-    ((read_calibration_area() >> 0) & 0b111) as u8
+pub fn adc_linearity() -> u8 {
+    read_calibration().adc_linearity
 }
 
 /// The ADC bias calibration value.
 ///
 /// Bits 5:3; to be stored into `ADC.calib.biascomp`.
-pub(crate) fn adc_bias() -> u8 {
-    ((read_calibration_area() >> 3) & 0b111) as u8
+pub fn adc_bias() -> u8 {
+    read_calibration().adc_bias
 }
 
 /// The 32kHz internal oscillator calibration value.
 ///
 /// Bits 12:6; to be stored into `OSC32KCTRL.osc32k.calib`.
-pub(crate) fn osc32k() -> u8 {
-    ((read_calibration_area() >> 6) & 0b111_1111) as u8
+pub fn osc32k() -> u8 {
+    read_calibration().osc32k
 }
 
 /// The USB TRANSN calibration value.
 ///
 /// Bits 17:13; to be stored into `USB.$mode().padcal.transn`.
-pub(crate) fn usb_transn() -> u8 {
-    ((read_calibration_area() >> 13) & 0b1_1111) as u8
+pub fn usb_transn() -> u8 {
+    read_calibration().usb_transn
 }
 
 /// The USB TRANSP calibration value.
 ///
 /// Bits 22:18; to be stored into `USB.$mode().padcal.transp`.
-pub(crate) fn usb_transp() -> u8 {
-    ((read_calibration_area() >> 18) & 0b1_1111) as u8
+pub fn usb_transp() -> u8 {
+    read_calibration().usb_transp
 }
 
 /// The USB TRIM calibration value.
 ///
 /// Bits 25:23; to be stored into `USB.$mode().padcal.trim`.
-pub(crate) fn usb_trim() -> u8 {
-    ((read_calibration_area() >> 23) & 0b111) as u8
+pub fn usb_trim() -> u8 {
+    read_calibration().usb_trim
 }
 
 /// The DFLL48M coarse calibration value.
 ///
 /// Bits 31:26; to be stored into `OSCCTRL.dfllval.coarse`.
-pub(crate) fn dfll48m_coarse() -> u8 {
-    ((read_calibration_area() >> 26) & 0b11_1111) as u8
+pub fn dfll48m_coarse() -> u8 {
+    read_calibration().dfll48m_coarse
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{CalibrationData, extract_field};
+
+    #[test]
+    fn test_extract_field() {
+        let area = 0b1010_1010_1101_0101_0100_1010_1010_1010;
+        assert_eq!(extract_field(area, 0, 3), 0b010);
+        assert_eq!(extract_field(area, 3, 3), 0b101);
+        assert_eq!(extract_field(area, 6, 7), 0b0101010);
+        assert_eq!(extract_field(area, 13, 5), 0b01010);
+        assert_eq!(extract_field(area, 18, 5), 0b10101);
+        assert_eq!(extract_field(area, 23, 3), 0b101);
+        assert_eq!(extract_field(area, 26, 6), 0b101010);
+    }
+
+    #[test]
+    fn test_from_area() {
+        let data = CalibrationData::from_area(0b1010_1010_1101_0101_0100_1010_1010_1010);
+        assert_eq!(data.adc_linearity, 0b010);
+        assert_eq!(data.adc_bias, 0b101);
+        assert_eq!(data.osc32k, 0b0101010);
+        assert_eq!(data.usb_transn, 0b01010);
+        assert_eq!(data.usb_transp, 0b10101);
+        assert_eq!(data.usb_trim, 0b101);
+        assert_eq!(data.dfll48m_coarse, 0b101010);
+    }
 }
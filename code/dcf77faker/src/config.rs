@@ -0,0 +1,396 @@
+//! Board-specific configuration, stored in the NVM.
+//!
+//! The configuration is stored in the last row of the RWWEE section (see [`crate::nvm`]). If that
+//! row does not contain a valid configuration (e.g. because it has never been written),
+//! [`Config::DEFAULT`] is used.
+//!
+//! The configuration can be changed on the debug UART: holding the reset-seconds button during
+//! startup opens a console that understands the following commands, one per line:
+//!
+//! | command                    | effect                                                    |
+//! | -------------------------- | --------------------------------------------------------- |
+//! | `show`                     | outputs the current configuration                         |
+//! | `address <addr>`           | sets the 7-bit I<sup>2</sup>C address of the display      |
+//! | `fast-i2c on\|off`         | sets whether the I<sup>2</sup>C bus runs at 400 kHz       |
+//! | `backlight on\|off`        | sets whether the backlight is on outside the night hours  |
+//! | `backlight-level <level>`  | sets the brightness of the backlight (0 to 255)           |
+//! | `summer-time on\|off`      | sets whether to switch between CET and CEST               |
+//! | `debug-log on\|off`        | sets whether the state is logged to the UART every second |
+//! | `rtc-correction <counts>`  | sets the RTC frequency correction (-127 to 127)           |
+//! | `night-start <hour>`       | sets the hour at which the backlight turns off (0 to 23)  |
+//! | `night-end <hour>`         | sets the hour at which the backlight turns on (0 to 23)   |
+//! | `defaults`                 | reverts to the default configuration                      |
+//! | `save`                     | stores the configuration in the NVM                       |
+//! | `exit`                     | leaves the console and starts up with the configuration   |
+//!
+//! Numbers may be given in decimal or, prefixed with `0x`, in hexadecimal. Changes that have not
+//! been saved only apply until the next reset.
+//!
+//! The configuration record has the following layout (in 32-bit little-endian words):
+//!
+//! | word | contents                                                                   |
+//! | ---- | -------------------------------------------------------------------------- |
+//! | 0    | magic value and layout version ([`MAGIC_AND_VERSION`])                     |
+//! | 1    | display address (7:0), flags (15:8), RTC correction (23:16)                |
+//! | 2    | night start hour (7:0), night end hour (15:8), backlight level (23:16)     |
+//! | 3    | bitwise complement of the XOR of words 0 through 2                         |
+
+
+use core::fmt::Write;
+
+use atsaml21g18b::Peripherals;
+
+use crate::nvm::{
+    CMD_RWWEE_ERASE_ROW, CMD_RWWEE_WRITE_PAGE, CONFIG_PAGE, execute_command, fill_page_buffer,
+    page_address, read_words,
+};
+use crate::uart::{SercomUart, UartWriter};
+
+
+/// The number of 32-bit words in a record.
+const RECORD_WORDS: usize = 4;
+
+/// The value identifying a valid configuration record of the current layout.
+///
+/// The lowest byte is the layout version.
+const MAGIC_AND_VERSION: u32 = 0xDCF7_7C03;
+
+const FLAG_BACKLIGHT: u32 = 1 << 0;
+const FLAG_FOLLOW_SUMMER_TIME: u32 = 1 << 1;
+const FLAG_DEBUG_LOG: u32 = 1 << 2;
+const FLAG_FAST_I2C: u32 = 1 << 3;
+
+/// The maximum length of a console line, in bytes.
+const MAX_LINE_LENGTH: usize = 32;
+
+
+/// The board-specific configuration.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct Config {
+    /// The 7-bit I<sup>2</sup>C address of the display.
+    pub display_address: u8,
+
+    /// Whether the I<sup>2</sup>C bus runs in fast mode (400 kHz) instead of at 100 kHz.
+    ///
+    /// This shortens display updates but requires short, well pulled-up bus lines.
+    pub fast_i2c: bool,
+
+    /// Whether the display backlight is on outside the night hours.
+    pub backlight: bool,
+
+    /// Whether to switch between CET and CEST (see
+    /// [`Dcf77Data::follow_summer_time`](crate::dcf77::Dcf77Data::follow_summer_time)).
+    pub follow_summer_time: bool,
+
+    /// Whether the current time, frame and temperature are written to the debug UART every second.
+    pub debug_log: bool,
+
+    /// The frequency correction of the RTC, in counts per 2<sup>20</sup> (about 0.95 ppm each).
+    ///
+    /// Positive values slow the RTC down (for a crystal that runs fast), negative values speed it
+    /// up. Must be between -127 and 127.
+    pub rtc_correction: i8,
+
+    /// The hour (0 to 23) from which on the backlight is kept off (see [`crate::backlight`]).
+    ///
+    /// If this equals [`night_end_hour`](Self::night_end_hour), there is no night.
+    pub night_start_hour: u8,
+
+    /// The hour (0 to 23) from which on the backlight is on again.
+    pub night_end_hour: u8,
+
+    /// The brightness of the backlight while it is on, from 0 (off) to 255 (fully on).
+    ///
+    /// Levels in between are produced by switching the backlight on and off rapidly; see
+    /// [`crate::i2c_display::GenericI2cDisplay::set_backlight_level`]. This costs power: while the
+    /// backlight is dimmed, the CPU is woken up 1024 times per second (see
+    /// [`crate::rtc::set_periodic_interrupt`]) and sends an I2C write whenever the backlight is
+    /// switched, which undoes most of the savings of sleeping in standby. The backlight is only
+    /// attached to the PCF8574, so the switching cannot be left to a timer running without the CPU.
+    pub backlight_level: u8,
+}
+impl Config {
+    /// The configuration used if none has been stored.
+    pub const DEFAULT: Self = Self {
+        display_address: 0b010_0111,
+        fast_i2c: false,
+        backlight: true,
+        follow_summer_time: true,
+        debug_log: false,
+        rtc_correction: 0,
+        night_start_hour: 0,
+        night_end_hour: 0,
+        backlight_level: u8::MAX,
+    };
+
+    /// Reads the stored configuration, falling back to [`DEFAULT`](Self::DEFAULT) if there is none.
+    pub fn load() -> Self {
+        decode(&read_words(CONFIG_PAGE))
+            .unwrap_or(Self::DEFAULT)
+    }
+
+    /// Stores the configuration in the NVM.
+    ///
+    /// Nothing is written once the brown-out detector has signalled a low supply voltage.
+    pub fn store(&self, peripherals: &mut Peripherals) {
+        if crate::init::brown_out_detected() {
+            return;
+        }
+
+        let address = page_address(CONFIG_PAGE);
+        execute_command(peripherals, CMD_RWWEE_ERASE_ROW, address);
+        fill_page_buffer(peripherals, CONFIG_PAGE, &encode(self));
+        execute_command(peripherals, CMD_RWWEE_WRITE_PAGE, address);
+    }
+
+    /// Applies a console command, returning what the console should do next.
+    fn execute(&mut self, line: &str) -> Result<ConsoleAction, &'static str> {
+        let mut words = line.split_ascii_whitespace();
+        let command = match words.next() {
+            Some(c) => c,
+            None => return Ok(ConsoleAction::Continue),
+        };
+        let argument = words.next();
+        if words.next().is_some() {
+            return Err("too many arguments");
+        }
+
+        match (command, argument) {
+            ("show", None) => {},
+            ("address", Some(arg)) => {
+                let address = parse_number(arg).ok_or("invalid number")?;
+                if !(0x08..=0x77).contains(&address) {
+                    return Err("address must be between 0x08 and 0x77");
+                }
+                self.display_address = address as u8;
+            },
+            ("fast-i2c", Some(arg)) => {
+                self.fast_i2c = parse_on_off(arg)?;
+            },
+            ("backlight", Some(arg)) => {
+                self.backlight = parse_on_off(arg)?;
+            },
+            ("backlight-level", Some(arg)) => {
+                // parse_number only accepts values that fit into a u8
+                self.backlight_level = parse_number(arg).ok_or("level must be between 0 and 255")? as u8;
+            },
+            ("summer-time", Some(arg)) => {
+                self.follow_summer_time = parse_on_off(arg)?;
+            },
+            ("debug-log", Some(arg)) => {
+                self.debug_log = parse_on_off(arg)?;
+            },
+            ("rtc-correction", Some(arg)) => {
+                let correction = match arg.strip_prefix('-') {
+                    Some(magnitude) => -parse_number(magnitude).ok_or("invalid number")?,
+                    None => parse_number(arg).ok_or("invalid number")?,
+                };
+                if !(-127..=127).contains(&correction) {
+                    return Err("correction must be between -127 and 127");
+                }
+                self.rtc_correction = correction as i8;
+            },
+            ("night-start", Some(arg)) => {
+                self.night_start_hour = parse_hour(arg)?;
+            },
+            ("night-end", Some(arg)) => {
+                self.night_end_hour = parse_hour(arg)?;
+            },
+            ("defaults", None) => {
+                *self = Self::DEFAULT;
+            },
+            ("save", None) => return Ok(ConsoleAction::Save),
+            ("exit", None) => return Ok(ConsoleAction::Exit),
+            _ => return Err("unknown command"),
+        }
+        Ok(ConsoleAction::Show)
+    }
+}
+
+
+/// What the console does after a command.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum ConsoleAction {
+    Continue,
+    Show,
+    Save,
+    Exit,
+}
+
+
+const fn checksum(words: &[u32; RECORD_WORDS]) -> u32 {
+    let mut value = 0;
+    let mut i = 0;
+    while i < RECORD_WORDS - 1 {
+        value ^= words[i];
+        i += 1;
+    }
+    !value
+}
+
+
+const fn encode(config: &Config) -> [u32; RECORD_WORDS] {
+    let mut flags = 0;
+    if config.backlight {
+        flags |= FLAG_BACKLIGHT;
+    }
+    if config.follow_summer_time {
+        flags |= FLAG_FOLLOW_SUMMER_TIME;
+    }
+    if config.debug_log {
+        flags |= FLAG_DEBUG_LOG;
+    }
+    if config.fast_i2c {
+        flags |= FLAG_FAST_I2C;
+    }
+
+    let mut words = [
+        MAGIC_AND_VERSION,
+        (config.display_address as u32)
+            | (flags << 8)
+            | (((config.rtc_correction as u8) as u32) << 16),
+        (config.night_start_hour as u32)
+            | ((config.night_end_hour as u32) << 8)
+            | ((config.backlight_level as u32) << 16),
+        0,
+    ];
+    words[RECORD_WORDS - 1] = checksum(&words);
+    words
+}
+
+
+const fn decode(words: &[u32; RECORD_WORDS]) -> Option<Config> {
+    if words[0] != MAGIC_AND_VERSION || words[RECORD_WORDS - 1] != checksum(words) {
+        return None;
+    }
+
+    let display_address = (words[1] & 0xFF) as u8;
+    let flags = (words[1] >> 8) & 0xFF;
+    let rtc_correction = ((words[1] >> 16) & 0xFF) as u8 as i8;
+    if display_address & 0b1000_0000 != 0 || rtc_correction == i8::MIN {
+        return None;
+    }
+
+    let night_start_hour = (words[2] & 0xFF) as u8;
+    let night_end_hour = ((words[2] >> 8) & 0xFF) as u8;
+    if night_start_hour > 23 || night_end_hour > 23 {
+        return None;
+    }
+    let backlight_level = ((words[2] >> 16) & 0xFF) as u8;
+
+    Some(Config {
+        display_address,
+        fast_i2c: flags & FLAG_FAST_I2C != 0,
+        backlight: flags & FLAG_BACKLIGHT != 0,
+        follow_summer_time: flags & FLAG_FOLLOW_SUMMER_TIME != 0,
+        debug_log: flags & FLAG_DEBUG_LOG != 0,
+        rtc_correction,
+        night_start_hour,
+        night_end_hour,
+        backlight_level,
+    })
+}
+
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number.
+fn parse_number(s: &str) -> Option<i32> {
+    let value = match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok()?,
+        None => s.parse::<u8>().ok()?,
+    };
+    Some(value.into())
+}
+
+
+fn parse_hour(s: &str) -> Result<u8, &'static str> {
+    let hour = parse_number(s).ok_or("invalid number")?;
+    if hour > 23 {
+        return Err("hour must be between 0 and 23");
+    }
+    Ok(hour as u8)
+}
+
+
+fn parse_on_off(s: &str) -> Result<bool, &'static str> {
+    match s {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err("expected on or off"),
+    }
+}
+
+
+fn show<U: SercomUart>(peripherals: &mut Peripherals, config: &Config) {
+    let on_off = |b| if b { "on" } else { "off" };
+    let mut uart = UartWriter::<U>::new(peripherals);
+    let _ = writeln!(uart, "address 0x{:02X}", config.display_address);
+    let _ = writeln!(uart, "fast-i2c {}", on_off(config.fast_i2c));
+    let _ = writeln!(uart, "backlight {}", on_off(config.backlight));
+    let _ = writeln!(uart, "backlight-level {}", config.backlight_level);
+    let _ = writeln!(uart, "summer-time {}", on_off(config.follow_summer_time));
+    let _ = writeln!(uart, "debug-log {}", on_off(config.debug_log));
+    let _ = writeln!(uart, "rtc-correction {}", config.rtc_correction);
+    let _ = writeln!(uart, "night-start {}", config.night_start_hour);
+    let _ = writeln!(uart, "night-end {}", config.night_end_hour);
+}
+
+
+/// Reads a line from the UART, echoing it back.
+///
+/// Backspace removes the last character; characters beyond [`MAX_LINE_LENGTH`] are dropped.
+/// Returns the number of bytes in the line.
+fn read_line<U: SercomUart>(peripherals: &mut Peripherals, buffer: &mut [u8; MAX_LINE_LENGTH]) -> usize {
+    let mut length = 0;
+    loop {
+        let b = U::read_byte(peripherals);
+        match b {
+            b'\r' | b'\n' => {
+                U::write_bytes(peripherals, *b"\r\n");
+                return length;
+            },
+            0x08 | 0x7F => {
+                if length > 0 {
+                    length -= 1;
+                    U::write_bytes(peripherals, *b"\x08 \x08");
+                }
+            },
+            0x20..=0x7E if length < MAX_LINE_LENGTH => {
+                buffer[length] = b;
+                length += 1;
+                U::write_bytes(peripherals, [b]);
+            },
+            _ => {},
+        }
+    }
+}
+
+
+/// Runs the configuration console on the given UART until `exit` is entered.
+///
+/// The UART must be set up and the watchdog must not be running yet.
+pub(crate) fn run_console<U: SercomUart>(peripherals: &mut Peripherals, config: &mut Config) {
+    let _ = writeln!(UartWriter::<U>::new(peripherals), "configuration console; enter exit to start");
+    show::<U>(peripherals, config);
+
+    let mut buffer = [0u8; MAX_LINE_LENGTH];
+    loop {
+        U::write_bytes(peripherals, *b"> ");
+        let length = read_line::<U>(peripherals, &mut buffer);
+
+        // read_line only accepts printable ASCII
+        let line = core::str::from_utf8(&buffer[..length]).unwrap_or("");
+        match config.execute(line) {
+            Ok(ConsoleAction::Continue) => {},
+            Ok(ConsoleAction::Show) => show::<U>(peripherals, config),
+            Ok(ConsoleAction::Save) => {
+                config.store(peripherals);
+                let result = if Config::load() == *config { "saved" } else { "saving failed" };
+                let _ = writeln!(UartWriter::<U>::new(peripherals), "{}", result);
+            },
+            Ok(ConsoleAction::Exit) => return,
+            Err(message) => {
+                let _ = writeln!(UartWriter::<U>::new(peripherals), "error: {}", message);
+            },
+        }
+    }
+}
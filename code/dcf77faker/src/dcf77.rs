@@ -1,16 +1,259 @@
 //! The DCF77 time transmission protocol.
 
 
+use core::cmp::Ordering;
+use core::fmt;
+use core::time::Duration;
+
+
 pub const FREQUENCY_HZ: u32 = 77_500;
 
+/// The offset of Central European Time (CET) from UTC, in seconds.
+const CET_OFFSET_SECS: i64 = 60 * 60;
+
+/// The offset of Central European Summer Time (CEST) from UTC, in seconds.
+const CEST_OFFSET_SECS: i64 = 2 * 60 * 60;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The bits of [`Dcf77Data::civil_warning`] that are transmitted.
+const CIVIL_WARNING_MASK: u16 = 0b11_1111_1111_1111;
+
+/// The first frame bit and the width in bits of each field of a DCF77 frame, in the order of
+/// transmission. Bits beyond the width of a field are not transmitted.
+const FRAME_FIELDS: [(u32, u32); 17] = [
+    (1, 14), // civil warning
+    (15, 1), // abnormal operation
+    (16, 1), // summer time announcement
+    (17, 1), // CEST
+    (18, 1), // CET
+    (19, 1), // leap second announcement
+    (21, 4), // minute ones
+    (25, 3), // minute tens
+    (29, 4), // hour ones
+    (33, 2), // hour tens
+    (36, 4), // day of month ones
+    (40, 2), // day of month tens
+    (42, 3), // day of week
+    (45, 4), // month ones
+    (49, 1), // month ten
+    (50, 4), // year in century ones
+    (54, 4), // year in century tens
+];
+
+/// The first frame bit covered by each parity bit and the frame bit of the parity bit itself.
+const PARITY_BITS: [(u32, u32); 3] = [
+    (21, 28), // minute
+    (29, 35), // hour
+    (36, 58), // date
+];
+
+
+/// Returns the number of days between 1970-01-01 and the given date of the proleptic Gregorian
+/// calendar.
+const fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    // shift the year to start in March, which moves the leap day to the end
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + (day as i64) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+
+/// Returns the year, month and day of the proleptic Gregorian calendar that is the given number of
+/// days after 1970-01-01. This is the inverse of [`days_from_civil`].
+const fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u8;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u8;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+
+/// Returns the day of the week (Monday = 1 to Sunday = 7) of the given number of days after
+/// 1970-01-01.
+const fn day_of_week_from_days(days: i64) -> u8 {
+    // 1970-01-01 was a Thursday
+    ((days + 3).rem_euclid(7) + 1) as u8
+}
+
+
+/// Returns the Unix timestamp at which summer time starts (if `october` is false) or ends (if
+/// `october` is true) in the given year.
+///
+/// Summer time in the EU starts on the last Sunday of March and ends on the last Sunday of October,
+/// both at 01:00 UTC.
+const fn summer_time_switch(year: i64, october: bool) -> i64 {
+    let last_day = days_from_civil(year, if october { 10 } else { 3 }, 31);
+    // 1970-01-01 was a Thursday; 0 = Sunday
+    let weekday = (last_day + 4).rem_euclid(7);
+    (last_day - weekday) * SECS_PER_DAY + 60 * 60
+}
+
+
+/// Whether the given year is a leap year according to the Gregorian calendar.
+pub const fn is_leap_year(year: u16) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+
+/// The number of days in the given month (1 to 12) of the given year.
+pub const fn days_in_month(year: u16, month: u8) -> u8 {
+    month_length(month, is_leap_year(year))
+}
+
+
+/// Whether the year with the given BCD digits, taken to be in the 21st century (20xx), is a leap
+/// year.
+pub const fn is_leap_year_bcd(year_in_century_tens: u8, year_in_century_ones: u8) -> bool {
+    is_leap_year(2000 + (year_in_century_tens as u16) * 10 + (year_in_century_ones as u16))
+}
+
+
+/// The number of days in the month (1 to 12) with the given BCD digits, in a leap year if `leap` is
+/// set.
+pub const fn days_in_month_bcd(month_ten: bool, month_ones: u8, leap: bool) -> u8 {
+    let month_tens = if month_ten { 10 } else { 0 };
+    month_length(month_ones.wrapping_add(month_tens), leap)
+}
+
+
+/// The number of days in the given month (1 to 12).
+const fn month_length(month: u8, leap: bool) -> u8 {
+    match month {
+        2 => if leap { 29 } else { 28 },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+
+/// The field of [`Dcf77Data`] that contains an invalid value.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Dcf77FieldError {
+    /// The civil warning bits do not fit into 14 bits.
+    CivilWarning,
+
+    /// The minute is not a valid BCD number from 0 to 59.
+    Minute,
+
+    /// The hour is not a valid BCD number from 0 to 23.
+    Hour,
+
+    /// The day of the month is not a valid BCD number or does not exist in the month.
+    DayOfMonth,
+
+    /// The day of the week is not between 1 (Monday) and 7 (Sunday).
+    DayOfWeek,
+
+    /// The month is not a valid BCD number from 1 to 12.
+    Month,
+
+    /// The year within the century is not a valid BCD number from 0 to 99.
+    YearInCentury,
+}
+impl fmt::Display for Dcf77FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CivilWarning
+                => write!(f, "invalid civil warning bits"),
+            Self::Minute
+                => write!(f, "invalid minute"),
+            Self::Hour
+                => write!(f, "invalid hour"),
+            Self::DayOfMonth
+                => write!(f, "invalid day of month"),
+            Self::DayOfWeek
+                => write!(f, "invalid day of week"),
+            Self::Month
+                => write!(f, "invalid month"),
+            Self::YearInCentury
+                => write!(f, "invalid year in century"),
+        }
+    }
+}
+
+
+/// The reason why a DCF77 frame could not be decoded.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Dcf77DecodeError {
+    /// Bit 0 (start of minute) is set.
+    StartOfMinuteSet,
+
+    /// Bit 20 (start of time information) is not set.
+    StartOfTimeClear,
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub(crate) struct Dcf77Data {
+    /// Bits beyond bit 58 are set.
+    ExcessBits,
+
+    /// The minute bits and their parity bit (bit 28) contain an odd number of ones.
+    MinuteParity,
+
+    /// The hour bits and their parity bit (bit 35) contain an odd number of ones.
+    HourParity,
+
+    /// The date bits and their parity bit (bit 58) contain an odd number of ones.
+    DateParity,
+
+    /// A field contains a value that is not a valid BCD digit or out of range for the field, such
+    /// as minute 75 or month 13.
+    InvalidValue(Dcf77FieldError),
+}
+impl fmt::Display for Dcf77DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartOfMinuteSet
+                => write!(f, "start-of-minute bit set"),
+            Self::StartOfTimeClear
+                => write!(f, "start-of-time bit clear"),
+            Self::ExcessBits
+                => write!(f, "bits beyond the frame set"),
+            Self::MinuteParity
+                => write!(f, "minute parity mismatch"),
+            Self::HourParity
+                => write!(f, "hour parity mismatch"),
+            Self::DateParity
+                => write!(f, "date parity mismatch"),
+            Self::InvalidValue(field)
+                => write!(f, "{}", field),
+        }
+    }
+}
+
+
+/// The error returned when setting a field of [`Dcf77Data`] to a value outside of its range.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OutOfRangeError;
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "value out of range")
+    }
+}
+
+
+/// The information transmitted in a DCF77 frame.
+///
+/// This deliberately does not implement [`Ord`]: an ordering over the fields would not be
+/// chronological. Use [`cmp_time`](Self::cmp_time) to compare times.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Dcf77Data {
     // start of minute (bit :00) is always 0
 
     /// Civil warning bits. (bits :01 through :14)
     ///
-    /// Only the bottom 14 bits of this value are used.
+    /// The content of these bits is defined by the operator of the transmitter; they carry the
+    /// encrypted Meteotime weather data and, formerly, civil defense warnings. Only the bottom 14
+    /// bits of this value are transmitted, and [`check`](Self::check) rejects any other bits; use
+    /// [`set_civil_warning_raw`](Self::set_civil_warning_raw) to mask them off.
     pub civil_warning: u16,
 
     /// Abnormal transmitter operation. (bit :15)
@@ -18,7 +261,9 @@ pub(crate) struct Dcf77Data {
 
     /// Summer time announcement. (bit :16)
     ///
-    /// Set during the hour before a summer <-> winter time changeover.
+    /// Set during the hour before a summer <-> winter time changeover, i.e. in the 60 frames
+    /// transmitted during that hour; these describe the minutes up to and including the first
+    /// minute after the changeover.
     pub summer_announcement: bool,
 
     /// Set if CEST (summer time) is in effect. (bit :17)
@@ -29,7 +274,9 @@ pub(crate) struct Dcf77Data {
 
     /// Leap second announcement. (bit :19)
     ///
-    /// Set during the hour before the insertion of a leap second.
+    /// Set during the hour before the insertion of a leap second. The transmitter inserts the
+    /// leap second if this is still set in the minute starting at 00:00 UTC on the first of a
+    /// month, and clears it afterwards.
     pub leap_second_announcement: bool,
 
     // start of time (bit :20) is always 1
@@ -97,6 +344,20 @@ pub(crate) struct Dcf77Data {
     // bit :58 is even parity over date bits :36 through :57
 
     // on bit :59, modulation is fully disabled
+
+    /// The century of the year, e.g. 20 for the years 2000 through 2099. (not transmitted)
+    ///
+    /// DCF77 only transmits the year within its century; receivers must pick the century by their
+    /// own convention. The faker keeps it locally so that date calculations (most importantly,
+    /// whether a year ending in 00 is a leap year) are correct in every century.
+    pub century: u8,
+
+    /// Whether the switches between summer and winter time are applied when the time is
+    /// incremented. (not transmitted)
+    ///
+    /// If this is unset, the time stays in its current zone and the summer time announcement is
+    /// never set, e.g. to simulate a transmitter that is stuck in one zone.
+    pub follow_summer_time: bool,
 }
 impl Dcf77Data {
     pub const fn new() -> Self {
@@ -118,10 +379,117 @@ impl Dcf77Data {
             month_ten: false,
             year_in_century_ones: 0,
             year_in_century_tens: 9,
+            century: 19,
+            follow_summer_time: true,
+        }
+    }
+
+    /// Advances the time by one minute, applying the switches between summer and winter time.
+    ///
+    /// Summer time starts on the last Sunday of March, when 02:00 CET is skipped and the minute
+    /// after 01:59 CET is 03:00 CEST. It ends on the last Sunday of October, when the minute after
+    /// 02:59 CEST is 02:00 CET, so that the hour from 02:00 to 02:59 is transmitted twice, first in
+    /// CEST and then in CET. The summer time announcement is updated accordingly.
+    ///
+    /// If [`follow_summer_time`](Self::follow_summer_time) is unset, the time zone is never
+    /// switched and the announcement is cleared instead.
+    ///
+    /// At midnight, the time zone is also set according to the date (see
+    /// [`update_dst`](Self::update_dst)), so that a time that was set in the wrong zone is
+    /// corrected within a day.
+    pub const fn increment_minute(&mut self) {
+        self.advance_minute();
+        if self.follow_summer_time {
+            if self.hour() == 0 && self.minute() == 0 {
+                self.update_dst();
+            }
+            self.apply_dst_rules();
+        } else {
+            self.summer_announcement = false;
         }
     }
 
-    pub fn increment_minute(&mut self) {
+    /// Sets the time zone to the one in effect at the current date and time according to the EU
+    /// rules: CEST from 02:00 CET on the last Sunday of March to 03:00 CEST on the last Sunday of
+    /// October, CET otherwise.
+    ///
+    /// The hour from 02:00 to 02:59 on the last Sunday of October occurs once in each zone; during
+    /// it, the current zone is kept. The hour that is skipped on the last Sunday of March is taken
+    /// to be CEST.
+    ///
+    /// The summer time announcement is updated as well (see
+    /// [`summer_announcement`](Self::summer_announcement)).
+    pub const fn update_dst(&mut self) {
+        let year = self.year() as i64;
+        let local_secs =
+            days_from_civil(year, self.month(), self.day_of_month()) * SECS_PER_DAY
+            + (self.hour() as i64) * 60 * 60
+            + (self.minute() as i64) * 60
+        ;
+
+        // in the local time of the zone that is left
+        let summer_start = summer_time_switch(year, false) + CET_OFFSET_SECS;
+        let summer_end = summer_time_switch(year, true) + CEST_OFFSET_SECS;
+
+        let repeated_hour = local_secs >= summer_end - 60 * 60 && local_secs < summer_end;
+        let cest = if repeated_hour && self.cet != self.cest {
+            self.cest
+        } else {
+            local_secs >= summer_start && local_secs < summer_end
+        };
+        self.cest = cest;
+        self.cet = !cest;
+        self.update_summer_announcement();
+    }
+
+    /// Whether the date is the last Sunday of the given month, assuming the month has 31 days.
+    const fn is_last_sunday_of(&self, month: u8) -> bool {
+        self.day_of_week == 7 && self.month() == month && self.day_of_month() >= 25
+    }
+
+    /// Switches between CET and CEST if the current minute is the one at which the switch happens,
+    /// and updates the summer time announcement.
+    ///
+    /// This is called after each minute has been advanced. Since each switch only happens from the
+    /// respective other zone, it happens exactly once; in particular, the repeated hour in October
+    /// is recognized as such because it is already in CET.
+    const fn apply_dst_rules(&mut self) {
+        let march = self.is_last_sunday_of(3);
+        let october = self.is_last_sunday_of(10);
+
+        if self.cet && march && self.hour() == 2 && self.minute() == 0 {
+            // 02:00 CET becomes 03:00 CEST
+            self.hour_ones = 3;
+            self.cet = false;
+            self.cest = true;
+        } else if self.cest && october && self.hour() == 3 && self.minute() == 0 {
+            // 03:00 CEST becomes 02:00 CET
+            self.hour_ones = 2;
+            self.cet = true;
+            self.cest = false;
+        }
+
+        self.update_summer_announcement();
+    }
+
+    /// Sets the summer time announcement if a switch between CET and CEST is imminent.
+    const fn update_summer_announcement(&mut self) {
+        let march = self.is_last_sunday_of(3);
+        let october = self.is_last_sunday_of(10);
+
+        // announce from the frame describing the first minute after the switch minus one hour up
+        // to and including the frame describing the first minute after the switch
+        let (hour, minute) = (self.hour(), self.minute());
+        self.summer_announcement =
+            (march && self.cet && hour == 1 && minute > 0)
+            || (march && self.cest && hour == 3 && minute == 0)
+            || (october && self.cest && hour == 2 && minute > 0)
+            || (october && self.cet && hour == 2 && minute == 0)
+        ;
+    }
+
+    /// Advances the time by one minute, ignoring summer time.
+    const fn advance_minute(&mut self) {
         self.minute_ones += 1;
         if self.minute_ones < 10 {
             return;
@@ -136,9 +504,10 @@ impl Dcf77Data {
         self.minute_tens = 0;
         self.hour_ones += 1;
         if self.hour_tens == 2 && self.hour_ones >= 4 {
-            // don't bother incrementing the date
             self.hour_ones = 0;
             self.hour_tens = 0;
+            self.advance_day();
+            self.recompute_day_of_week();
             return;
         } else if self.hour_ones < 10 {
             return;
@@ -146,216 +515,1551 @@ impl Dcf77Data {
 
         self.hour_ones = 0;
         self.hour_tens += 1;
+    }
+
+    /// Advances the date by one day, carrying into the month, the year and the century.
+    ///
+    /// The day of the week is not changed.
+    const fn advance_day(&mut self) {
+        if self.day_of_month() < self.days_in_month() {
+            self.day_of_month_ones += 1;
+            if self.day_of_month_ones == 10 {
+                self.day_of_month_ones = 0;
+                self.day_of_month_tens += 1;
+            }
+            return;
+        }
+
+        self.day_of_month_ones = 1;
+        self.day_of_month_tens = 0;
+        match self.month() {
+            9 => {
+                self.month_ones = 0;
+                self.month_ten = true;
+                return;
+            },
+            12 => {
+                self.month_ones = 1;
+                self.month_ten = false;
+            },
+            _ => {
+                self.month_ones += 1;
+                return;
+            },
+        }
+
+        self.year_in_century_ones += 1;
+        if self.year_in_century_ones < 10 {
+            return;
+        }
 
-        // don't bother with the date
+        self.year_in_century_ones = 0;
+        self.year_in_century_tens += 1;
+        if self.year_in_century_tens < 10 {
+            return;
+        }
+
+        self.year_in_century_tens = 0;
+        self.century += 1;
     }
 
-    pub const fn to_bits(&self) -> u64 {
-        let mut value = 0;
+    /// Moves the date back by one day, borrowing from the month, the year and the century.
+    ///
+    /// The day of the week is not changed.
+    fn retreat_day(&mut self) {
+        if self.day_of_month() > 1 {
+            if self.day_of_month_ones > 0 {
+                self.day_of_month_ones -= 1;
+            } else {
+                self.day_of_month_ones = 9;
+                self.day_of_month_tens -= 1;
+            }
+            return;
+        }
+
+        match self.month() {
+            1 => {
+                self.month_ones = 2;
+                self.month_ten = true;
+                if self.year_in_century_ones > 0 {
+                    self.year_in_century_ones -= 1;
+                } else {
+                    self.year_in_century_ones = 9;
+                    if self.year_in_century_tens > 0 {
+                        self.year_in_century_tens -= 1;
+                    } else {
+                        self.year_in_century_tens = 9;
+                        self.century -= 1;
+                    }
+                }
+            },
+            10 => {
+                self.month_ones = 9;
+                self.month_ten = false;
+            },
+            _ => {
+                self.month_ones -= 1;
+            },
+        }
 
-        // bit 0 is always 0
+        let last_day = self.days_in_month();
+        self.day_of_month_tens = last_day / 10;
+        self.day_of_month_ones = last_day % 10;
+    }
 
-        // bits 1 through 14
-        value |= ((self.civil_warning & 0b11_1111_1111_1111) as u64) << 1;
+    /// Sets the day of the week to the one of the date.
+    pub const fn recompute_day_of_week(&mut self) {
+        let days = days_from_civil(self.year() as i64, self.month(), self.day_of_month());
+        self.day_of_week = day_of_week_from_days(days);
+    }
 
-        // bit 15
-        if self.abnormal_operation {
-            value |= 1 << 15;
+    /// Advances the hour by one, wrapping around from 23 to 00.
+    ///
+    /// The minute, the date and the time zone are left unchanged; this is meant for setting the
+    /// clock by hand.
+    pub fn increment_hour(&mut self) {
+        self.hour_ones += 1;
+        if self.hour_tens == 2 && self.hour_ones >= 4 {
+            self.hour_ones = 0;
+            self.hour_tens = 0;
+        } else if self.hour_ones >= 10 {
+            self.hour_ones = 0;
+            self.hour_tens += 1;
         }
+    }
 
-        // bit 16
-        if self.summer_announcement {
-            value |= 1 << 16;
+    /// Moves the time back by one minute, wrapping around from 00:00 to 23:59 of the previous day.
+    ///
+    /// This is the inverse of [`increment_minute`](Self::increment_minute): the minute before
+    /// 03:00 CEST on the last Sunday of March is 01:59 CET, the minute before the second 02:00 (in
+    /// CET) on the last Sunday of October is 02:59 CEST, and the summer time announcement is
+    /// updated accordingly. If [`follow_summer_time`](Self::follow_summer_time) is unset, the time
+    /// zone is never switched and the announcement is cleared instead.
+    pub fn decrement_minute(&mut self) {
+        if self.follow_summer_time {
+            self.undo_dst_rules();
+        }
+        self.retreat_minute();
+        if self.follow_summer_time {
+            self.update_summer_announcement();
+        } else {
+            self.summer_announcement = false;
         }
+    }
+
+    /// Switches back between CET and CEST if the current minute is the first one after a switch,
+    /// so that moving back by a minute arrives at the last minute before the switch.
+    ///
+    /// This undoes [`apply_dst_rules`](Self::apply_dst_rules) and is called before each minute is
+    /// moved back.
+    fn undo_dst_rules(&mut self) {
+        let march = self.is_last_sunday_of(3);
+        let october = self.is_last_sunday_of(10);
 
-        // bit 17
-        if self.cest {
-            value |= 1 << 17;
+        if self.cest && march && self.hour() == 3 && self.minute() == 0 {
+            // the minute before 03:00 CEST is 01:59 CET
+            self.hour_ones = 2;
+            self.cet = true;
+            self.cest = false;
+        } else if self.cet && october && self.hour() == 2 && self.minute() == 0 {
+            // the minute before 02:00 CET is 02:59 CEST
+            self.hour_ones = 3;
+            self.cet = false;
+            self.cest = true;
         }
+    }
 
-        // bit 18
-        if self.cet {
-            value |= 1 << 18;
+    /// Moves the time back by one minute, ignoring summer time.
+    fn retreat_minute(&mut self) {
+        if self.minute_ones > 0 {
+            self.minute_ones -= 1;
+            return;
         }
 
-        // bit 19
-        if self.leap_second_announcement {
-            value |= 1 << 19;
+        self.minute_ones = 9;
+        if self.minute_tens > 0 {
+            self.minute_tens -= 1;
+            return;
         }
 
-        // bit 20
-        value |= 1 << 20;
+        self.minute_tens = 5;
+        if self.hour_ones > 0 {
+            self.hour_ones -= 1;
+            return;
+        }
 
-        // bits 21 through 27
-        let mut minute_parity = false;
-        if self.minute_ones & 1 != 0 {
-            value |= 1 << 21;
-            minute_parity = !minute_parity;
+        if self.hour_tens > 0 {
+            self.hour_ones = 9;
+            self.hour_tens -= 1;
+            return;
         }
-        if self.minute_ones & 2 != 0 {
-            value |= 1 << 22;
-            minute_parity = !minute_parity;
+
+        self.hour_ones = 3;
+        self.hour_tens = 2;
+        self.retreat_day();
+        self.recompute_day_of_week();
+    }
+
+    /// Returns a builder to construct a validated time from decimal values.
+    pub const fn builder() -> Dcf77DataBuilder {
+        Dcf77DataBuilder::new()
+    }
+
+    /// Sets the minute (0 to 59).
+    pub fn set_minute(&mut self, minute: u8) -> Result<(), OutOfRangeError> {
+        if minute > 59 {
+            return Err(OutOfRangeError);
         }
-        if self.minute_ones & 4 != 0 {
-            value |= 1 << 23;
-            minute_parity = !minute_parity;
+        self.minute_tens = minute / 10;
+        self.minute_ones = minute % 10;
+        Ok(())
+    }
+
+    /// Sets the hour (0 to 23).
+    ///
+    /// The time zone is left unchanged.
+    pub fn set_hour(&mut self, hour: u8) -> Result<(), OutOfRangeError> {
+        if hour > 23 {
+            return Err(OutOfRangeError);
         }
-        if self.minute_ones & 8 != 0 {
-            value |= 1 << 24;
-            minute_parity = !minute_parity;
+        self.hour_tens = hour / 10;
+        self.hour_ones = hour % 10;
+        Ok(())
+    }
+
+    /// Sets the date, given as the day of the month, the month (1 to 12) and the year within the
+    /// [`century`](Self::century) (0 to 99), and updates the day of the week accordingly.
+    ///
+    /// Nothing is changed if the date does not exist.
+    pub fn set_date(&mut self, day_of_month: u8, month: u8, year_in_century: u8) -> Result<(), OutOfRangeError> {
+        if !(1..=12).contains(&month) || year_in_century > 99 {
+            return Err(OutOfRangeError);
         }
-        if self.minute_tens & 1 != 0 {
-            value |= 1 << 25;
-            minute_parity = !minute_parity;
+        let year = (self.century as u16) * 100 + (year_in_century as u16);
+        if day_of_month < 1 || day_of_month > days_in_month(year, month) {
+            return Err(OutOfRangeError);
         }
-        if self.minute_tens & 2 != 0 {
-            value |= 1 << 26;
-            minute_parity = !minute_parity;
+
+        self.day_of_month_tens = day_of_month / 10;
+        self.day_of_month_ones = day_of_month % 10;
+        self.month_ten = month >= 10;
+        self.month_ones = month % 10;
+        self.year_in_century_tens = year_in_century / 10;
+        self.year_in_century_ones = year_in_century % 10;
+        self.recompute_day_of_week();
+        Ok(())
+    }
+
+    /// The civil warning bits (see [`civil_warning`](Self::civil_warning)), masked to the 14 bits
+    /// that are transmitted.
+    pub const fn civil_warning_raw(&self) -> u16 {
+        self.civil_warning & CIVIL_WARNING_MASK
+    }
+
+    /// Sets the civil warning bits (see [`civil_warning`](Self::civil_warning)), dropping all but
+    /// the bottom 14 bits.
+    pub fn set_civil_warning_raw(&mut self, bits: u16) {
+        self.civil_warning = bits & CIVIL_WARNING_MASK;
+    }
+
+    /// Sets whether abnormal transmitter operation is signalled. (bit :15)
+    ///
+    /// The real transmitter uses this "call bit" to alert the PTB staff to irregularities such as
+    /// running on a backup antenna. A faker can set it to simulate a transmitter fault and check how
+    /// a receiver reacts; most receivers ignore it, some flag the reception as unreliable.
+    pub fn set_abnormal_operation(&mut self, on: bool) {
+        self.abnormal_operation = on;
+    }
+
+    /// Returns a copy of this value that signals abnormal transmitter operation, for setting up
+    /// test scenarios. See [`set_abnormal_operation`](Self::set_abnormal_operation).
+    pub const fn with_abnormal_operation(mut self) -> Self {
+        self.abnormal_operation = true;
+        self
+    }
+
+    /// Converts a Unix timestamp (seconds since 1970-01-01 00:00:00 UTC) into the corresponding
+    /// German legal time, as transmitted by DCF77.
+    ///
+    /// DCF77 transmits Central European Time (CET, UTC+1) or, between the last Sunday of March and
+    /// the last Sunday of October, Central European Summer Time (CEST, UTC+2); the zone is chosen
+    /// according to the current EU rules and `cet` or `cest` set accordingly. The summer time
+    /// announcement is set in the 60 minutes up to and including the first minute after a switch
+    /// (see [`summer_announcement`](Self::summer_announcement)). The seconds are dropped; leap seconds,
+    /// civil warnings and abnormal operation are never signalled.
+    pub fn from_unix_timestamp(secs: i64) -> Dcf77Data {
+        let (utc_year, _, _) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+        let summer_start = summer_time_switch(utc_year, false);
+        let summer_end = summer_time_switch(utc_year, true);
+        let cest = secs >= summer_start && secs < summer_end;
+        let minute_secs = secs - secs.rem_euclid(60);
+        let summer_announcement =
+            (minute_secs > summer_start - 60 * 60 && minute_secs <= summer_start)
+            || (minute_secs > summer_end - 60 * 60 && minute_secs <= summer_end)
+        ;
+
+        let local_secs = secs + if cest { CEST_OFFSET_SECS } else { CET_OFFSET_SECS };
+        let days = local_secs.div_euclid(SECS_PER_DAY);
+        let secs_of_day = local_secs.rem_euclid(SECS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        let hour = (secs_of_day / (60 * 60)) as u8;
+        let minute = ((secs_of_day / 60) % 60) as u8;
+        let year_in_century = year.rem_euclid(100) as u8;
+
+        let day_of_week = day_of_week_from_days(days);
+
+        Dcf77Data {
+            civil_warning: 0,
+            abnormal_operation: false,
+            summer_announcement,
+            cest,
+            cet: !cest,
+            leap_second_announcement: false,
+            minute_ones: minute % 10,
+            minute_tens: minute / 10,
+            hour_ones: hour % 10,
+            hour_tens: hour / 10,
+            day_of_month_ones: day % 10,
+            day_of_month_tens: day / 10,
+            day_of_week,
+            month_ones: month % 10,
+            month_ten: month >= 10,
+            year_in_century_ones: year_in_century % 10,
+            year_in_century_tens: year_in_century / 10,
+            century: year.div_euclid(100) as u8,
+            follow_summer_time: true,
         }
-        if self.minute_tens & 4 != 0 {
-            value |= 1 << 27;
-            minute_parity = !minute_parity;
+    }
+
+    /// The minute (0 to 59).
+    pub const fn minute(&self) -> u8 {
+        self.minute_tens * 10 + self.minute_ones
+    }
+
+    /// The hour (0 to 23).
+    pub const fn hour(&self) -> u8 {
+        self.hour_tens * 10 + self.hour_ones
+    }
+
+    /// The day of the month (1 to 31).
+    pub const fn day_of_month(&self) -> u8 {
+        self.day_of_month_tens * 10 + self.day_of_month_ones
+    }
+
+    /// The month (1 to 12).
+    pub const fn month(&self) -> u8 {
+        (if self.month_ten { 10 } else { 0 }) + self.month_ones
+    }
+
+    /// The year within its century (0 to 99).
+    pub const fn year_in_century(&self) -> u8 {
+        self.year_in_century_tens * 10 + self.year_in_century_ones
+    }
+
+    /// The full year, including the century.
+    pub const fn year(&self) -> u16 {
+        (self.century as u16) * 100 + (self.year_in_century() as u16)
+    }
+
+    /// Whether the year is a leap year according to the Gregorian calendar.
+    pub const fn is_leap_year(&self) -> bool {
+        is_leap_year(self.year())
+    }
+
+    /// The number of days in the month.
+    pub const fn days_in_month(&self) -> u8 {
+        days_in_month(self.year(), self.month())
+    }
+
+    /// Compares the date and time chronologically.
+    ///
+    /// The full year (including the [`century`](Self::century)), month, day, hour and minute are
+    /// compared in that order. If these are equal, CEST sorts before CET, which is chronological in
+    /// the hour that is repeated when summer time ends. All other fields are ignored.
+    pub fn cmp_time(&self, other: &Dcf77Data) -> Ordering {
+        let key = |data: &Dcf77Data| (
+            data.year(), data.month(), data.day_of_month(), data.hour(), data.minute(), data.cet,
+        );
+        key(self).cmp(&key(other))
+    }
+
+    /// The offset of the transmitted time from UTC, in hours: +1 for CET, +2 for CEST.
+    ///
+    /// If neither or both of [`cet`](Self::cet) and [`cest`](Self::cest) are set, the frame is
+    /// malformed; standard time (+1) is assumed in that case.
+    pub const fn utc_offset_hours(&self) -> i8 {
+        match (self.cet, self.cest) {
+            (false, true) => 2,
+            _ => 1,
         }
+    }
+
+    /// Returns the bits of the frame that is transmitted during the minute described by this
+    /// value.
+    ///
+    /// A DCF77 frame announces the time that comes into effect at the next minute marker, so this
+    /// is the encoding of the following minute.
+    pub const fn frame_bits(&self) -> u64 {
+        let mut next_minute = *self;
+        next_minute.increment_minute();
+        next_minute.to_bits()
+    }
 
-        // bit 28
-        if minute_parity {
-            value |= 1 << 28;
+    /// The nominal length of the amplitude reduction at the start of the given second (0 to 59) of
+    /// the frame encoding this time: 100 ms for a 0 bit and 200 ms for a 1 bit.
+    ///
+    /// Returns `None` for the minute marker (second 59), which has no reduction. Note that during a
+    /// minute, the frame of the following minute is transmitted (see
+    /// [`frame_bits`](Self::frame_bits)).
+    pub const fn second_pulse_length(&self, second: u8) -> Option<Duration> {
+        if second >= 59 {
+            return None;
+        }
+        if (self.to_bits() >> second) & 0b1 != 0 {
+            Some(Duration::from_millis(200))
+        } else {
+            Some(Duration::from_millis(100))
         }
+    }
 
-        // bits 29 thorugh 34
-        let mut hour_parity = false;
-        if self.hour_ones & 1 != 0 {
-            value |= 1 << 29;
-            hour_parity = !hour_parity;
+    /// The bits of the frame encoding this time in the order of transmission, from bit 0 to bit 58.
+    pub fn bits_iter(&self) -> impl Iterator<Item = bool> {
+        let bits = self.to_bits();
+        (0..59).map(move |i| (bits >> i) & 0b1 != 0)
+    }
+
+    /// Decodes a frame as encoded by [`to_bits`](Self::to_bits).
+    ///
+    /// The century is not transmitted; it is taken to be the one between 1900 and 2299 in which the
+    /// date falls on the transmitted day of the week (as all four centuries start on different
+    /// days of the week), or 20 if there is none.
+    pub fn from_bits(bits: u64) -> Result<Dcf77Data, Dcf77DecodeError> {
+        if bits >> 59 != 0 {
+            return Err(Dcf77DecodeError::ExcessBits);
         }
-        if self.hour_ones & 2 != 0 {
-            value |= 1 << 30;
-            hour_parity = !hour_parity;
+        if bits & (1 << 0) != 0 {
+            return Err(Dcf77DecodeError::StartOfMinuteSet);
         }
-        if self.hour_ones & 4 != 0 {
-            value |= 1 << 31;
-            hour_parity = !hour_parity;
+        if bits & (1 << 20) == 0 {
+            return Err(Dcf77DecodeError::StartOfTimeClear);
         }
-        if self.hour_ones & 8 != 0 {
-            value |= 1 << 32;
-            hour_parity = !hour_parity;
+
+        let parity_errors = [
+            Dcf77DecodeError::MinuteParity,
+            Dcf77DecodeError::HourParity,
+            Dcf77DecodeError::DateParity,
+        ];
+        for (&(first_bit, parity_bit), error) in PARITY_BITS.iter().zip(parity_errors) {
+            let covered = (bits >> first_bit) & ((1 << (parity_bit - first_bit + 1)) - 1);
+            if !covered.count_ones().is_multiple_of(2) {
+                return Err(error);
+            }
         }
-        if self.hour_tens & 1 != 0 {
-            value |= 1 << 33;
-            hour_parity = !hour_parity;
+
+        // same order as FRAME_FIELDS
+        let mut values = [0u16; FRAME_FIELDS.len()];
+        for (value, &(first_bit, width)) in values.iter_mut().zip(FRAME_FIELDS.iter()) {
+            *value = ((bits >> first_bit) & ((1 << width) - 1)) as u16;
         }
-        if self.hour_tens & 2 != 0 {
-            value |= 1 << 34;
-            hour_parity = !hour_parity;
+        let mut data = Dcf77Data {
+            civil_warning: values[0],
+            abnormal_operation: values[1] != 0,
+            summer_announcement: values[2] != 0,
+            cest: values[3] != 0,
+            cet: values[4] != 0,
+            leap_second_announcement: values[5] != 0,
+            minute_ones: values[6] as u8,
+            minute_tens: values[7] as u8,
+            hour_ones: values[8] as u8,
+            hour_tens: values[9] as u8,
+            day_of_month_ones: values[10] as u8,
+            day_of_month_tens: values[11] as u8,
+            day_of_week: values[12] as u8,
+            month_ones: values[13] as u8,
+            month_ten: values[14] != 0,
+            year_in_century_ones: values[15] as u8,
+            year_in_century_tens: values[16] as u8,
+            century: 20,
+            follow_summer_time: true,
+        };
+
+        data.check()
+            .map_err(Dcf77DecodeError::InvalidValue)?;
+
+        let transmitted_day_of_week = data.day_of_week;
+        for century in 19..=22 {
+            data.century = century;
+            data.recompute_day_of_week();
+            if data.day_of_week == transmitted_day_of_week && data.check().is_ok() {
+                return Ok(data);
+            }
         }
+        data.century = 20;
+        data.day_of_week = transmitted_day_of_week;
+        Ok(data)
+    }
 
-        // bit 35
-        if hour_parity {
-            value |= 1 << 35;
+    /// Checks that every field is within its range, returning the first one that is not.
+    ///
+    /// The day of the week is only checked for its range, not against the date.
+    pub const fn check(&self) -> Result<(), Dcf77FieldError> {
+        if self.civil_warning & !CIVIL_WARNING_MASK != 0 {
+            return Err(Dcf77FieldError::CivilWarning);
         }
+        if self.minute_tens > 5 || self.minute_ones > 9 {
+            return Err(Dcf77FieldError::Minute);
+        }
+        if self.hour_tens > 2 || self.hour_ones > 9 || self.hour() > 23 {
+            return Err(Dcf77FieldError::Hour);
+        }
+        if self.month_ones > 9 || self.month() < 1 || self.month() > 12 {
+            return Err(Dcf77FieldError::Month);
+        }
+        if self.year_in_century_tens > 9 || self.year_in_century_ones > 9 {
+            return Err(Dcf77FieldError::YearInCentury);
+        }
+        if self.day_of_month_tens > 3
+            || self.day_of_month_ones > 9
+            || self.day_of_month() < 1
+            || self.day_of_month() > self.days_in_month()
+        {
+            return Err(Dcf77FieldError::DayOfMonth);
+        }
+        if self.day_of_week < 1 || self.day_of_week > 7 {
+            return Err(Dcf77FieldError::DayOfWeek);
+        }
+        Ok(())
+    }
+
+    /// Encodes the frame describing this time.
+    ///
+    /// In debug builds, this panics if a field is out of range (see [`check`](Self::check)).
+    pub const fn to_bits(&self) -> u64 {
+        debug_assert!(self.check().is_ok(), "field out of range");
+        self.encode()
+    }
+
+    /// Encodes the frame describing this time, truncating each field to its width.
+    const fn encode(&self) -> u64 {
+        // same order as FRAME_FIELDS
+        let values = [
+            self.civil_warning as u64,
+            self.abnormal_operation as u64,
+            self.summer_announcement as u64,
+            self.cest as u64,
+            self.cet as u64,
+            self.leap_second_announcement as u64,
+            self.minute_ones as u64,
+            self.minute_tens as u64,
+            self.hour_ones as u64,
+            self.hour_tens as u64,
+            self.day_of_month_ones as u64,
+            self.day_of_month_tens as u64,
+            self.day_of_week as u64,
+            self.month_ones as u64,
+            self.month_ten as u64,
+            self.year_in_century_ones as u64,
+            self.year_in_century_tens as u64,
+        ];
+
+        // bit 0 is always 0, bit 20 is always 1
+        let mut value = 1 << 20;
 
-        // bits 36 through 41
-        let mut date_parity = false;
-        if self.day_of_month_ones & 1 != 0 {
-            value |= 1 << 36;
-            date_parity = !date_parity;
+        let mut i = 0;
+        while i < FRAME_FIELDS.len() {
+            let (first_bit, width) = FRAME_FIELDS[i];
+            value |= (values[i] & ((1 << width) - 1)) << first_bit;
+            i += 1;
         }
-        if self.day_of_month_ones & 2 != 0 {
-            value |= 1 << 37;
-            date_parity = !date_parity;
+
+        // each parity bit makes the number of ones from its first bit up to itself even
+        let mut i = 0;
+        while i < PARITY_BITS.len() {
+            let (first_bit, parity_bit) = PARITY_BITS[i];
+            let covered = (value >> first_bit) & ((1 << (parity_bit - first_bit)) - 1);
+            value |= ((covered.count_ones() & 1) as u64) << parity_bit;
+            i += 1;
         }
-        if self.day_of_month_ones & 4 != 0 {
-            value |= 1 << 38;
-            date_parity = !date_parity;
+
+        value
+    }
+}
+impl Default for Dcf77Data {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// The time zone, as far as summer time is concerned.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Dst {
+    /// Central European Time (CET, UTC+1).
+    Winter,
+
+    /// Central European Summer Time (CEST, UTC+2).
+    Summer,
+}
+
+
+/// Constructs a validated [`Dcf77Data`] from decimal values.
+///
+/// Fields that are not set are taken from [`Dcf77Data::new`]. The day of the week and the summer
+/// time announcement are derived from the other fields.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Dcf77DataBuilder {
+    hour: u8,
+    minute: u8,
+    day_of_month: u8,
+    month: u8,
+    year: u16,
+    dst: Dst,
+    civil_warning: u16,
+    abnormal_operation: bool,
+    leap_second_announcement: bool,
+}
+impl Dcf77DataBuilder {
+    /// Creates a builder starting from the time of [`Dcf77Data::new`].
+    pub const fn new() -> Self {
+        let data = Dcf77Data::new();
+        Self {
+            hour: data.hour(),
+            minute: data.minute(),
+            day_of_month: data.day_of_month(),
+            month: data.month(),
+            year: data.year(),
+            dst: if data.cest { Dst::Summer } else { Dst::Winter },
+            civil_warning: data.civil_warning,
+            abnormal_operation: data.abnormal_operation,
+            leap_second_announcement: data.leap_second_announcement,
         }
-        if self.day_of_month_ones & 8 != 0 {
-            value |= 1 << 39;
-            date_parity = !date_parity;
+    }
+
+    /// Sets the hour (0 to 23) and the minute (0 to 59).
+    pub const fn time(mut self, hour: u8, minute: u8) -> Self {
+        self.hour = hour;
+        self.minute = minute;
+        self
+    }
+
+    /// Sets the day of the month, the month (1 to 12) and the full year (including the century).
+    pub const fn date(mut self, day_of_month: u8, month: u8, year: u16) -> Self {
+        self.day_of_month = day_of_month;
+        self.month = month;
+        self.year = year;
+        self
+    }
+
+    /// Sets the time zone.
+    pub const fn dst(mut self, dst: Dst) -> Self {
+        self.dst = dst;
+        self
+    }
+
+    /// Sets the civil warning bits (14 bits).
+    pub const fn civil_warning(mut self, civil_warning: u16) -> Self {
+        self.civil_warning = civil_warning;
+        self
+    }
+
+    /// Sets whether abnormal transmitter operation is signalled.
+    pub const fn abnormal_operation(mut self, abnormal_operation: bool) -> Self {
+        self.abnormal_operation = abnormal_operation;
+        self
+    }
+
+    /// Sets whether a leap second is announced.
+    pub const fn leap_second_announcement(mut self, leap_second_announcement: bool) -> Self {
+        self.leap_second_announcement = leap_second_announcement;
+        self
+    }
+
+    /// Constructs the data, checking every field (see [`Dcf77Data::check`]).
+    pub fn build(&self) -> Result<Dcf77Data, Dcf77FieldError> {
+        if self.year / 100 > u8::MAX.into() {
+            return Err(Dcf77FieldError::YearInCentury);
         }
-        if self.day_of_month_tens & 1 != 0 {
-            value |= 1 << 40;
-            date_parity = !date_parity;
+        let year_in_century = (self.year % 100) as u8;
+
+        let mut data = Dcf77Data {
+            civil_warning: self.civil_warning,
+            abnormal_operation: self.abnormal_operation,
+            summer_announcement: false,
+            cest: self.dst == Dst::Summer,
+            cet: self.dst == Dst::Winter,
+            leap_second_announcement: self.leap_second_announcement,
+            minute_ones: self.minute % 10,
+            minute_tens: self.minute / 10,
+            hour_ones: self.hour % 10,
+            hour_tens: self.hour / 10,
+            day_of_month_ones: self.day_of_month % 10,
+            day_of_month_tens: self.day_of_month / 10,
+            day_of_week: 1,
+            // months from 20 on end up with an invalid ones digit instead of a valid month
+            month_ones: if self.month >= 10 { self.month - 10 } else { self.month },
+            month_ten: self.month >= 10,
+            year_in_century_ones: year_in_century % 10,
+            year_in_century_tens: year_in_century / 10,
+            century: (self.year / 100) as u8,
+            follow_summer_time: true,
+        };
+        data.check()?;
+
+        data.recompute_day_of_week();
+        data.update_summer_announcement();
+        Ok(data)
+    }
+}
+impl Default for Dcf77DataBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use core::cmp::Ordering;
+    use core::time::Duration;
+
+    use super::{
+        civil_from_days, days_from_civil, days_in_month, days_in_month_bcd, is_leap_year,
+        is_leap_year_bcd, Dcf77Data, Dcf77DataBuilder, Dcf77DecodeError, Dcf77FieldError, Dst,
+        OutOfRangeError,
+    };
+
+    fn data_at(hour: u8, minute: u8) -> Dcf77Data {
+        let mut data = Dcf77Data::new();
+        data.hour_tens = hour / 10;
+        data.hour_ones = hour % 10;
+        data.minute_tens = minute / 10;
+        data.minute_ones = minute % 10;
+        data
+    }
+
+    fn on_date(mut data: Dcf77Data, day_of_month: u8, month: u8, day_of_week: u8) -> Dcf77Data {
+        data.day_of_month_tens = day_of_month / 10;
+        data.day_of_month_ones = day_of_month % 10;
+        data.month_ten = month >= 10;
+        data.month_ones = month % 10;
+        data.day_of_week = day_of_week;
+        data
+    }
+
+    fn in_year(mut data: Dcf77Data, year: u16) -> Dcf77Data {
+        data.century = (year / 100) as u8;
+        data.year_in_century_tens = ((year / 10) % 10) as u8;
+        data.year_in_century_ones = (year % 10) as u8;
+        data
+    }
+
+    fn cet(mut data: Dcf77Data) -> Dcf77Data {
+        data.cet = true;
+        data.cest = false;
+        data
+    }
+
+    /// Returns whether the given range of bits contains an even number of ones.
+    fn has_even_parity(bits: u64, first_bit: u32, last_bit: u32) -> bool {
+        let mask = (1u64 << (last_bit + 1)) - (1u64 << first_bit);
+        (bits & mask).count_ones() & 1 == 0
+    }
+
+    #[test]
+    fn test_abnormal_operation() {
+        let data = Dcf77Data::new();
+        assert_eq!(data.to_bits() & (1 << 15), 0);
+
+        let abnormal = data.with_abnormal_operation();
+        assert_eq!(abnormal.to_bits(), data.to_bits() | (1 << 15));
+
+        let mut normal = abnormal;
+        normal.set_abnormal_operation(false);
+        assert_eq!(normal, data);
+    }
+
+    #[test]
+    fn test_decoded_accessors() {
+        // 10:40, 10.04.(19)90
+        let data = Dcf77Data::new();
+        assert_eq!(data.minute(), 40);
+        assert_eq!(data.hour(), 10);
+        assert_eq!(data.day_of_month(), 10);
+        assert_eq!(data.month(), 4);
+        assert_eq!(data.year_in_century(), 90);
+
+        let data = data_at(23, 59);
+        assert_eq!(data.hour(), 23);
+        assert_eq!(data.minute(), 59);
+    }
+
+    #[test]
+    fn test_to_bits() {
+        // CEST, 10:40, Tuesday, 10.04.(19)90
+        let data = Dcf77Data::new();
+        assert_eq!(
+            data.to_bits(),
+            0b110_0100_0000_1000_1001_0000_1010_0001_1000_0001_0010_0000_0000_0000_0000,
+        );
+    }
+
+    #[test]
+    fn test_to_bits_markers() {
+        let bits = Dcf77Data::new().to_bits();
+
+        // start of minute is 0, start of time is 1, the 59th second is not encoded
+        assert_eq!(bits & (1 << 0), 0);
+        assert_ne!(bits & (1 << 20), 0);
+        assert_eq!(bits >> 59, 0);
+    }
+
+    #[test]
+    fn test_to_bits_truncates_fields() {
+        // to_bits asserts that all fields are in range in debug builds; encode is what it runs after
+        // the check
+        let data = Dcf77Data {
+            civil_warning: u16::MAX,
+            abnormal_operation: true,
+            summer_announcement: true,
+            cest: true,
+            cet: true,
+            leap_second_announcement: true,
+            minute_ones: u8::MAX,
+            minute_tens: u8::MAX,
+            hour_ones: u8::MAX,
+            hour_tens: u8::MAX,
+            day_of_month_ones: u8::MAX,
+            day_of_month_tens: u8::MAX,
+            day_of_week: u8::MAX,
+            month_ones: u8::MAX,
+            month_ten: true,
+            year_in_century_ones: u8::MAX,
+            year_in_century_tens: u8::MAX,
+            century: u8::MAX,
+            follow_summer_time: true,
+        };
+
+        // every data bit is set; the minute has 7 ones (odd), the hour 6 and the date 22 (both even)
+        assert_eq!(data.encode(), ((1 << 58) - 2) & !(1 << 35));
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Dcf77Data::new().check(), Ok(()));
+
+        let mut data = Dcf77Data::new();
+        data.minute_tens = 6;
+        assert_eq!(data.check(), Err(Dcf77FieldError::Minute));
+
+        let mut data = Dcf77Data::new();
+        data.hour_tens = 2;
+        data.hour_ones = 4;
+        assert_eq!(data.check(), Err(Dcf77FieldError::Hour));
+
+        let mut data = Dcf77Data::new();
+        data.month_ten = true;
+        data.month_ones = 5;
+        assert_eq!(data.check(), Err(Dcf77FieldError::Month));
+
+        // 1990 is not a leap year
+        let data = on_date(Dcf77Data::new(), 29, 2, 4);
+        assert_eq!(data.check(), Err(Dcf77FieldError::DayOfMonth));
+        let data = on_date(Dcf77Data::new(), 31, 4, 2);
+        assert_eq!(data.check(), Err(Dcf77FieldError::DayOfMonth));
+
+        let data = on_date(Dcf77Data::new(), 10, 4, 0);
+        assert_eq!(data.check(), Err(Dcf77FieldError::DayOfWeek));
+
+        let mut data = Dcf77Data::new();
+        data.civil_warning = 1 << 14;
+        assert_eq!(data.check(), Err(Dcf77FieldError::CivilWarning));
+
+        assert_eq!(Dcf77Data::default(), Dcf77Data::new());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "field out of range")]
+    fn test_to_bits_checks_fields() {
+        let mut data = Dcf77Data::new();
+        data.minute_tens = 6;
+        data.to_bits();
+    }
+
+    #[test]
+    fn test_civil_warning_raw() {
+        let mut data = Dcf77Data::new();
+        data.set_civil_warning_raw(0xFFFF);
+        assert_eq!(data.civil_warning, 0x3FFF);
+        assert_eq!(data.civil_warning_raw(), 0x3FFF);
+        assert_eq!(data.check(), Ok(()));
+
+        // the warning bits end right before the abnormal-operation bit
+        let bits = data.to_bits();
+        assert_eq!(bits & 0xFFFF, 0x3FFF << 1);
+
+        data.civil_warning = 0xC001;
+        assert_eq!(data.civil_warning_raw(), 0x0001);
+    }
+
+    #[test]
+    fn test_to_bits_parity() {
+        for hour in 0..24 {
+            for minute in 0..60 {
+                let bits = data_at(hour, minute).to_bits();
+                assert!(has_even_parity(bits, 21, 28));
+                assert!(has_even_parity(bits, 29, 35));
+                assert!(has_even_parity(bits, 36, 58));
+            }
         }
-        if self.day_of_month_tens & 2 != 0 {
-            value |= 1 << 41;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_bits_iter() {
+        let data = Dcf77Data::new();
+        let bits: Vec<bool> = data.bits_iter().collect();
+        assert_eq!(bits.len(), 59);
+        assert!(!bits[0]);
+        assert!(bits[20]);
+
+        let value = bits.iter().rev().fold(0u64, |value, &bit| (value << 1) | u64::from(bit));
+        assert_eq!(value, data.to_bits());
+    }
+
+    #[test]
+    fn test_second_pulse_length() {
+        // Wednesday, 2024-07-03 11:46 CEST
+        let data = Dcf77Data::from_unix_timestamp(1_720_000_000);
+        let one_bits = [
+            17, // CEST
+            20, // start of time
+            22, 23, 27, 28, // minute 46 (6 = 0b0110, 4 = 0b100) and its parity
+            29, 33, // hour 11 (even parity)
+            36, 37, // day of month 3
+            42, 43, // Wednesday (3)
+            45, 46, 47, // month 7
+            52, 55, // year 24
+            58, // date parity (9 ones)
+        ];
+        for second in 0..59 {
+            let expected = if one_bits.contains(&second) { 200 } else { 100 };
+            assert_eq!(data.second_pulse_length(second), Some(Duration::from_millis(expected)), "second {}", second);
         }
+        assert_eq!(data.second_pulse_length(59), None);
+    }
 
-        // bits 42 through 44
-        if self.day_of_week & 1 != 0 {
-            value |= 1 << 42;
-            date_parity = !date_parity;
+    #[test]
+    fn test_from_bits_round_trip() {
+        let timestamps = [
+            0, // Thursday, 1970-01-01 01:00 CET
+            1_711_846_740, // Sunday, 2024-03-31 01:59 CET, announcing summer time
+            1_720_000_000, // Wednesday, 2024-07-03 11:46 CEST
+            4_102_441_200, // Friday, 2100-01-01 00:00 CET
+        ];
+        for timestamp in timestamps {
+            let data = Dcf77Data::from_unix_timestamp(timestamp);
+            assert_eq!(Dcf77Data::from_bits(data.to_bits()), Ok(data));
         }
-        if self.day_of_week & 2 != 0 {
-            value |= 1 << 43;
-            date_parity = !date_parity;
+
+        let data = Dcf77Data::new().with_abnormal_operation();
+        assert_eq!(Dcf77Data::from_bits(data.to_bits()), Ok(data));
+    }
+
+    #[test]
+    fn test_from_bits_errors() {
+        let bits = Dcf77Data::new().to_bits();
+        assert_eq!(Dcf77Data::from_bits(bits | (1 << 0)), Err(Dcf77DecodeError::StartOfMinuteSet));
+        assert_eq!(Dcf77Data::from_bits(bits & !(1 << 20)), Err(Dcf77DecodeError::StartOfTimeClear));
+        assert_eq!(Dcf77Data::from_bits(bits | (1 << 59)), Err(Dcf77DecodeError::ExcessBits));
+        assert_eq!(Dcf77Data::from_bits(bits ^ (1 << 21)), Err(Dcf77DecodeError::MinuteParity));
+        assert_eq!(Dcf77Data::from_bits(bits ^ (1 << 35)), Err(Dcf77DecodeError::HourParity));
+        assert_eq!(Dcf77Data::from_bits(bits ^ (1 << 50)), Err(Dcf77DecodeError::DateParity));
+
+        // minute ones = 15 (even number of additional ones, so the parity still matches)
+        assert_eq!(
+            Dcf77Data::from_bits(bits | (0b1111 << 21)),
+            Err(Dcf77DecodeError::InvalidValue(Dcf77FieldError::Minute)),
+        );
+    }
+
+    #[test]
+    fn test_frame_announces_next_minute() {
+        assert_eq!(data_at(10, 40).frame_bits(), data_at(10, 41).to_bits());
+        assert_eq!(data_at(10, 59).frame_bits(), data_at(11, 0).to_bits());
+        assert_eq!(data_at(23, 59).frame_bits(), on_date(data_at(0, 0), 11, 4, 3).to_bits());
+    }
+
+    #[test]
+    fn test_decrement_inverts_increment() {
+        for hour in 0..24 {
+            for minute in 0..60 {
+                let original = data_at(hour, minute);
+
+                let mut data = original;
+                data.increment_minute();
+                data.decrement_minute();
+                assert_eq!(data, original);
+
+                data.decrement_minute();
+                data.increment_minute();
+                assert_eq!(data, original);
+            }
         }
-        if self.day_of_week & 4 != 0 {
-            value |= 1 << 44;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_decrement_inverts_increment_across_dates() {
+        // every 997 minutes over eight years, which hits every time of day and many month ends
+        let start = 946_681_200; // 2000-01-01 00:00 CET
+        for step in 0..(8 * 366 * 24 * 60 / 997) {
+            let original = Dcf77Data::from_unix_timestamp(start + step * 997 * 60);
+
+            let mut data = original;
+            data.increment_minute();
+            data.decrement_minute();
+            assert_eq!(data, original);
+
+            data.decrement_minute();
+            data.increment_minute();
+            assert_eq!(data, original);
         }
 
-        // bits 45 through 49
-        if self.month_ones & 1 != 0 {
-            value |= 1 << 45;
-            date_parity = !date_parity;
+        // the last minute of a leap day and of a century
+        for timestamp in [951_865_140, 4_102_441_140] {
+            let mut original = Dcf77Data::from_unix_timestamp(timestamp);
+            original.follow_summer_time = false;
+            let mut data = original;
+            data.increment_minute();
+            assert_ne!(data.day_of_month(), original.day_of_month());
+            data.decrement_minute();
+            assert_eq!(data, original);
         }
-        if self.month_ones & 2 != 0 {
-            value |= 1 << 46;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_decrement_borrows() {
+        let mut data = data_at(10, 0);
+        data.decrement_minute();
+        assert_eq!(data, data_at(9, 59));
+
+        let mut data = data_at(20, 30);
+        data.decrement_minute();
+        assert_eq!(data, data_at(20, 29));
+
+        let mut data = data_at(0, 0);
+        data.decrement_minute();
+        assert_eq!(data, on_date(data_at(23, 59), 9, 4, 1));
+    }
+
+    #[test]
+    fn test_date_rollover() {
+        // within a month
+        let mut data = on_date(data_at(23, 59), 9, 4, 1);
+        data.increment_minute();
+        assert_eq!((data.day_of_month_tens, data.day_of_month_ones), (1, 0));
+        assert_eq!(data.month(), 4);
+
+        // end of a 30-day month
+        let mut data = on_date(data_at(23, 59), 30, 4, 1);
+        data.increment_minute();
+        assert_eq!((data.day_of_month(), data.month()), (1, 5));
+
+        // end of September
+        let mut data = on_date(data_at(23, 59), 30, 9, 1);
+        data.increment_minute();
+        assert_eq!((data.day_of_month(), data.month_ten, data.month_ones), (1, true, 0));
+
+        // end of February in a common and in a leap year
+        let mut data = on_date(data_at(23, 59), 28, 2, 1);
+        data.increment_minute();
+        assert_eq!((data.day_of_month(), data.month()), (1, 3));
+        let mut data = on_date(data_at(23, 59), 28, 2, 1);
+        data.year_in_century_tens = 2;
+        data.year_in_century_ones = 4;
+        data.increment_minute();
+        assert_eq!((data.day_of_month(), data.month()), (29, 2));
+
+        // end of the year and of the century
+        let mut data = on_date(data_at(23, 59), 31, 12, 1);
+        data.year_in_century_tens = 9;
+        data.year_in_century_ones = 9;
+        data.increment_minute();
+        assert_eq!((data.day_of_month(), data.month(), data.year()), (1, 1, 2000));
+        assert!(data.year_in_century_ones < 10 && data.year_in_century_tens < 10);
+        data.decrement_minute();
+        assert_eq!((data.day_of_month(), data.month(), data.year()), (31, 12, 1999));
+    }
+
+    #[test]
+    fn test_day_of_week() {
+        // Tuesday, 1990-04-10
+        let mut data = Dcf77Data::new();
+        data.day_of_week = 0;
+        data.recompute_day_of_week();
+        assert_eq!(data.day_of_week, 2);
+
+        // Sunday, 2000-01-02 after Saturday, 2000-01-01 after Friday, 1999-12-31
+        let mut data = on_date(data_at(23, 59), 31, 12, 5);
+        data.year_in_century_tens = 9;
+        data.year_in_century_ones = 9;
+        data.increment_minute();
+        assert_eq!(data.day_of_week, 6);
+        for _ in 0..24 * 60 {
+            data.increment_minute();
         }
-        if self.month_ones & 4 != 0 {
-            value |= 1 << 47;
-            date_parity = !date_parity;
+        assert_eq!((data.day_of_month(), data.day_of_week), (2, 7));
+        data.increment_hour();
+        for _ in 0..23 * 60 {
+            data.increment_minute();
         }
-        if self.month_ones & 8 != 0 {
-            value |= 1 << 48;
-            date_parity = !date_parity;
+        assert_eq!((data.day_of_month(), data.day_of_week), (3, 1));
+
+        // and back again
+        for _ in 0..3 * 24 * 60 {
+            data.decrement_minute();
         }
-        if self.month_ten {
-            value |= 1 << 49;
-            date_parity = !date_parity;
+        assert_eq!((data.day_of_month(), data.month(), data.day_of_week), (31, 12, 5));
+    }
+
+    #[test]
+    fn test_setters() {
+        let mut data = Dcf77Data::new();
+        assert_eq!(data.set_minute(37), Ok(()));
+        assert_eq!(data.set_hour(21), Ok(()));
+        assert_eq!((data.minute_tens, data.minute_ones, data.hour_tens, data.hour_ones), (3, 7, 2, 1));
+        assert_eq!(data.set_minute(60), Err(OutOfRangeError));
+        assert_eq!(data.set_hour(24), Err(OutOfRangeError));
+        assert_eq!((data.hour(), data.minute()), (21, 37));
+
+        // Saturday, 1992-02-29
+        assert_eq!(data.set_date(29, 2, 92), Ok(()));
+        assert_eq!((data.day_of_month_tens, data.day_of_month_ones), (2, 9));
+        assert_eq!((data.month_ten, data.month_ones), (false, 2));
+        assert_eq!((data.year_in_century_tens, data.year_in_century_ones), (9, 2));
+        assert_eq!(data.day_of_week, 6);
+
+        // Tuesday, 1992-11-10
+        assert_eq!(data.set_date(10, 11, 92), Ok(()));
+        assert_eq!((data.month_ten, data.month_ones, data.day_of_week), (true, 1, 2));
+
+        let before = data;
+        assert_eq!(data.set_date(29, 2, 93), Err(OutOfRangeError));
+        assert_eq!(data.set_date(31, 4, 93), Err(OutOfRangeError));
+        assert_eq!(data.set_date(0, 1, 93), Err(OutOfRangeError));
+        assert_eq!(data.set_date(1, 13, 93), Err(OutOfRangeError));
+        assert_eq!(data.set_date(1, 1, 100), Err(OutOfRangeError));
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(Dcf77DataBuilder::new().build(), Ok(Dcf77Data::new()));
+
+        // Monday, 2024-07-15, 14:00 CEST
+        let data = Dcf77Data::builder()
+            .time(14, 0)
+            .date(15, 7, 2024)
+            .dst(Dst::Summer)
+            .civil_warning(0b101)
+            .build();
+        let mut expected = Dcf77Data::from_unix_timestamp(1_721_044_800);
+        expected.civil_warning = 0b101;
+        assert_eq!(data, Ok(expected));
+
+        // the announcement is derived: Sunday, 2024-03-31, 01:30 CET
+        let data = Dcf77Data::builder()
+            .time(1, 30)
+            .date(31, 3, 2024)
+            .dst(Dst::Winter)
+            .build()
+            .unwrap();
+        assert_eq!((data.day_of_week, data.cet, data.summer_announcement), (7, true, true));
+
+        let builder = Dcf77Data::builder();
+        assert_eq!(builder.time(24, 0).build(), Err(Dcf77FieldError::Hour));
+        assert_eq!(builder.time(12, 60).build(), Err(Dcf77FieldError::Minute));
+        assert_eq!(builder.date(1, 13, 2024).build(), Err(Dcf77FieldError::Month));
+        assert_eq!(builder.date(1, 20, 2024).build(), Err(Dcf77FieldError::Month));
+        assert_eq!(builder.date(29, 2, 2023).build(), Err(Dcf77FieldError::DayOfMonth));
+        assert_eq!(builder.date(1, 1, 30_000).build(), Err(Dcf77FieldError::YearInCentury));
+        assert_eq!(builder.civil_warning(1 << 14).build(), Err(Dcf77FieldError::CivilWarning));
+    }
+
+    #[test]
+    fn test_increment_hour() {
+        let mut data = data_at(8, 17);
+        data.increment_hour();
+        assert_eq!((data.hour(), data.minute()), (9, 17));
+        data.increment_hour();
+        assert_eq!((data.hour_tens, data.hour_ones), (1, 0));
+
+        let mut data = data_at(23, 59);
+        data.increment_hour();
+        assert_eq!((data.hour_tens, data.hour_ones, data.minute()), (0, 0, 59));
+        assert_eq!(data.day_of_month(), Dcf77Data::new().day_of_month());
+
+        // every hour is reached once, with valid BCD digits
+        let mut data = data_at(0, 0);
+        for hour in 1..=24 {
+            data.increment_hour();
+            assert!(data.hour_ones < 10);
+            assert_eq!(data.hour(), hour % 24);
+            assert!(has_even_parity(data.to_bits(), 29, 35));
         }
+    }
 
-        // bits 50 through 57
-        if self.year_in_century_ones & 1 != 0 {
-            value |= 1 << 50;
-            date_parity = !date_parity;
+    #[test]
+    fn test_civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        for days in [-719_468, -1, 0, 11_016, 11_017, 19_782, 47_540] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
         }
-        if self.year_in_century_ones & 2 != 0 {
-            value |= 1 << 51;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_from_unix_timestamp_winter() {
+        // 2024-01-01 00:00:00 UTC = 01:00 CET on a Monday
+        let data = Dcf77Data::from_unix_timestamp(1_704_067_200);
+        assert!(data.cet);
+        assert!(!data.cest);
+        assert!(!data.summer_announcement);
+        assert_eq!((data.hour(), data.minute()), (1, 0));
+        assert_eq!((data.day_of_month(), data.month(), data.year_in_century()), (1, 1, 24));
+        assert_eq!(data.year(), 2024);
+        assert_eq!(data.day_of_week, 1);
+    }
+
+    #[test]
+    fn test_from_unix_timestamp_summer() {
+        // 2024-07-15 12:00:00 UTC = 14:00 CEST on a Monday
+        let data = Dcf77Data::from_unix_timestamp(1_721_044_800);
+        assert!(!data.cet);
+        assert!(data.cest);
+        assert_eq!((data.hour(), data.minute()), (14, 0));
+        assert_eq!((data.day_of_month(), data.month(), data.year_in_century()), (15, 7, 24));
+        assert_eq!(data.day_of_week, 1);
+
+        // 23:59:59 UTC is already the next day in CEST
+        let data = Dcf77Data::from_unix_timestamp(1_721_044_800 + 12 * 60 * 60 - 1);
+        assert_eq!((data.hour(), data.minute()), (1, 59));
+        assert_eq!((data.day_of_month(), data.month()), (16, 7));
+        assert_eq!(data.day_of_week, 2);
+    }
+
+    #[test]
+    fn test_from_unix_timestamp_switch() {
+        // summer time starts on 2024-03-31 at 01:00 UTC (02:00 CET becomes 03:00 CEST)
+        let data = Dcf77Data::from_unix_timestamp(1_711_846_800 - 1);
+        assert!(data.cet);
+        assert!(data.summer_announcement);
+        assert_eq!((data.hour(), data.minute()), (1, 59));
+        let data = Dcf77Data::from_unix_timestamp(1_711_846_800);
+        assert!(data.cest);
+        assert!(data.summer_announcement);
+        assert_eq!((data.hour(), data.minute()), (3, 0));
+        assert_eq!(data.day_of_week, 7);
+        let data = Dcf77Data::from_unix_timestamp(1_711_846_800 + 60);
+        assert!(!data.summer_announcement);
+        let data = Dcf77Data::from_unix_timestamp(1_711_846_800 - 60 * 60);
+        assert!(!data.summer_announcement);
+
+        // summer time ends on 2024-10-27 at 01:00 UTC (03:00 CEST becomes 02:00 CET)
+        let data = Dcf77Data::from_unix_timestamp(1_729_990_800 - 1);
+        assert!(data.cest);
+        assert!(data.summer_announcement);
+        assert_eq!((data.hour(), data.minute()), (2, 59));
+        let data = Dcf77Data::from_unix_timestamp(1_729_990_800);
+        assert!(data.cet);
+        assert_eq!((data.hour(), data.minute()), (2, 0));
+    }
+
+    #[test]
+    fn test_leap_years() {
+        assert!(!is_leap_year(1900));
+        assert!(is_leap_year(1996));
+        assert!(is_leap_year(2000));
+        assert!(!is_leap_year(2023));
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(2100));
+
+        assert_eq!(days_in_month(2000, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2100, 4), 30);
+        assert_eq!(days_in_month(2100, 12), 31);
+    }
+
+    #[test]
+    fn test_bcd_leap_years() {
+        // the year in the century is taken to be in the 2000s
+        assert!(is_leap_year_bcd(0, 0));
+        assert!(!is_leap_year_bcd(2, 3));
+        assert!(is_leap_year_bcd(2, 4));
+        assert!(!is_leap_year_bcd(9, 9));
+
+        for leap in [false, true] {
+            assert_eq!(days_in_month_bcd(false, 1, leap), 31);
+            assert_eq!(days_in_month_bcd(false, 2, leap), if leap { 29 } else { 28 });
+            assert_eq!(days_in_month_bcd(false, 4, leap), 30);
+            assert_eq!(days_in_month_bcd(true, 1, leap), 30);
+            assert_eq!(days_in_month_bcd(true, 2, leap), 31);
         }
-        if self.year_in_century_ones & 4 != 0 {
-            value |= 1 << 52;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_days_in_month_of_data() {
+        let mut data = Dcf77Data::new();
+        data.century = 20;
+        for (year_in_century, leap) in [(23, false), (24, true)] {
+            data.year_in_century_tens = year_in_century / 10;
+            data.year_in_century_ones = year_in_century % 10;
+            assert_eq!(data.is_leap_year(), leap);
+
+            for (month, days) in [(1, 31), (2, if leap { 29 } else { 28 }), (4, 30)] {
+                data = on_date(data, 1, month, 1);
+                assert_eq!(data.days_in_month(), days);
+            }
         }
-        if self.year_in_century_ones & 8 != 0 {
-            value |= 1 << 53;
-            date_parity = !date_parity;
+    }
+
+    #[test]
+    fn test_century() {
+        let mut data = Dcf77Data::new();
+        data.year_in_century_tens = 0;
+        data.year_in_century_ones = 0;
+        data.month_ten = false;
+        data.month_ones = 2;
+
+        data.century = 20;
+        assert_eq!(data.year(), 2000);
+        assert!(data.is_leap_year());
+        assert_eq!(data.days_in_month(), 29);
+
+        data.century = 21;
+        assert_eq!(data.year(), 2100);
+        assert!(!data.is_leap_year());
+        assert_eq!(data.days_in_month(), 28);
+
+        // the century is not transmitted
+        let bits = data.to_bits();
+        data.century = 20;
+        assert_eq!(data.to_bits(), bits);
+    }
+
+    #[test]
+    fn test_cmp_time() {
+        // Friday, 1999-12-31, 23:59 CET
+        let mut before = cet(on_date(data_at(23, 59), 31, 12, 5));
+        before.year_in_century_tens = 9;
+        before.year_in_century_ones = 9;
+        let mut after = before;
+        after.increment_minute();
+        after = on_date(after, 1, 1, 6);
+        after.year_in_century_tens = 0;
+        after.year_in_century_ones = 0;
+        after.century = 20;
+        assert_eq!((after.hour(), after.minute()), (0, 0));
+
+        assert_eq!(before.cmp_time(&after), Ordering::Less);
+        assert_eq!(after.cmp_time(&before), Ordering::Greater);
+        assert_eq!(before.cmp_time(&before), Ordering::Equal);
+
+        // the field order alone would get this wrong
+        let mut warned = before;
+        warned.civil_warning = 0b1;
+        assert_eq!(warned.cmp_time(&after), Ordering::Less);
+        assert_eq!(warned.cmp_time(&before), Ordering::Equal);
+
+        // the repeated hour: 02:30 CEST comes before 02:30 CET
+        let first = Dcf77Data::from_unix_timestamp(1_729_989_000); // 2024-10-27 00:30 UTC
+        let second = Dcf77Data::from_unix_timestamp(1_729_992_600); // 2024-10-27 01:30 UTC
+        assert_eq!((first.hour(), first.minute()), (second.hour(), second.minute()));
+        assert_eq!(first.cmp_time(&second), Ordering::Less);
+    }
+
+    #[test]
+    fn test_utc_offset() {
+        let mut data = Dcf77Data::new();
+        assert_eq!(data.utc_offset_hours(), 2);
+        data.cest = false;
+        data.cet = true;
+        assert_eq!(data.utc_offset_hours(), 1);
+
+        // malformed frames fall back to standard time
+        data.cest = true;
+        assert_eq!(data.utc_offset_hours(), 1);
+        data.cet = false;
+        data.cest = false;
+        assert_eq!(data.utc_offset_hours(), 1);
+
+        let timestamp = 1_719_792_000; // 2024-07-01 00:00 UTC
+        assert_eq!(Dcf77Data::from_unix_timestamp(timestamp).utc_offset_hours(), 2);
+        let timestamp = 1_704_067_200; // 2024-01-01 00:00 UTC
+        assert_eq!(Dcf77Data::from_unix_timestamp(timestamp).utc_offset_hours(), 1);
+    }
+
+    #[test]
+    fn test_spring_forward() {
+        // Sunday, 2024-03-31, 01:58 CET
+        let mut data = cet(on_date(data_at(1, 58), 31, 3, 7));
+        let mut sequence = Vec::new();
+        for _ in 0..4 {
+            data.increment_minute();
+            sequence.push((data.hour(), data.minute(), data.cest));
         }
-        if self.year_in_century_tens & 1 != 0 {
-            value |= 1 << 54;
-            date_parity = !date_parity;
+        assert_eq!(sequence, [(1, 59, false), (3, 0, true), (3, 1, true), (3, 2, true)]);
+        assert!(!data.cet);
+        assert_eq!(data.day_of_month(), 31);
+
+        // the frame transmitted during 01:59 CET already announces 03:00 CEST
+        let data = cet(on_date(data_at(1, 59), 31, 3, 7));
+        let mut expected = on_date(data_at(3, 0), 31, 3, 7);
+        expected.summer_announcement = true;
+        assert_eq!(data.frame_bits(), expected.to_bits());
+
+        // not on other Sundays
+        let mut data = cet(on_date(data_at(1, 59), 24, 3, 7));
+        data.increment_minute();
+        assert_eq!((data.hour(), data.minute(), data.cet), (2, 0, true));
+    }
+
+    #[test]
+    fn test_fall_back() {
+        // Sunday, 2024-10-27, 01:58 CEST
+        let mut data = on_date(data_at(1, 58), 27, 10, 7);
+        let mut sequence = Vec::new();
+        for _ in 0..(2 * 60 + 4) {
+            data.increment_minute();
+            sequence.push((data.hour(), data.minute(), data.cest, data.summer_announcement));
         }
-        if self.year_in_century_tens & 2 != 0 {
-            value |= 1 << 55;
-            date_parity = !date_parity;
+        assert_eq!(sequence[0], (1, 59, true, false));
+        assert_eq!(sequence[1], (2, 0, true, false));
+        assert_eq!(sequence[2], (2, 1, true, true));
+        assert_eq!(sequence[60], (2, 59, true, true));
+        assert_eq!(sequence[61], (2, 0, false, true));
+        assert_eq!(sequence[62], (2, 1, false, false));
+        assert_eq!(sequence[120], (2, 59, false, false));
+        assert_eq!(sequence[121], (3, 0, false, false));
+        assert_eq!(sequence[123], (3, 2, false, false));
+        assert!(data.cet);
+        assert_eq!(data.day_of_month(), 27);
+        assert_eq!(data.day_of_week, 7);
+    }
+
+    #[test]
+    fn test_decrement_across_dst_switches() {
+        // Sunday, 2024-03-31, 01:58 CET and Sunday, 2024-10-27, 01:58 CEST
+        let spring = cet(on_date(data_at(1, 58), 31, 3, 7));
+        let fall = on_date(data_at(1, 58), 27, 10, 7);
+        for (mut start, minutes) in [(spring, 4), (fall, 2 * 60 + 4)] {
+            start.update_summer_announcement();
+            let mut forward = vec![start];
+            let mut data = start;
+            for _ in 0..minutes {
+                data.increment_minute();
+                forward.push(data);
+            }
+
+            // walking back visits the same minutes, including the zone and the announcement
+            let mut backward = vec![data];
+            for _ in 0..minutes {
+                data.decrement_minute();
+                backward.push(data);
+            }
+            backward.reverse();
+            assert_eq!(backward, forward);
         }
-        if self.year_in_century_tens & 4 != 0 {
-            value |= 1 << 56;
-            date_parity = !date_parity;
+
+        // 03:00 CEST in March follows 01:59 CET; the second 02:00 in October follows 02:59 CEST
+        let mut data = on_date(data_at(3, 0), 31, 3, 7);
+        data.decrement_minute();
+        assert_eq!((data.hour(), data.minute(), data.cet, data.summer_announcement), (1, 59, true, true));
+        let mut data = cet(on_date(data_at(2, 0), 27, 10, 7));
+        data.decrement_minute();
+        assert_eq!((data.hour(), data.minute(), data.cest, data.summer_announcement), (2, 59, true, true));
+    }
+
+    #[test]
+    fn test_update_dst() {
+        // Monday, 2024-07-15, 12:00 set up in CET
+        let mut data = cet(in_year(on_date(data_at(12, 0), 15, 7, 1), 2024));
+        data.update_dst();
+        assert!(data.cest && !data.cet);
+
+        // Monday, 2024-01-15, 12:00 set up in CEST
+        let mut data = in_year(on_date(data_at(12, 0), 15, 1, 1), 2024);
+        data.update_dst();
+        assert!(data.cet && !data.cest);
+
+        // Sunday, 2024-03-31: 01:59 is CET, the skipped 02:30 and 03:00 are CEST
+        for (hour, minute, cest) in [(1, 59, false), (2, 30, true), (3, 0, true)] {
+            let mut data = in_year(on_date(data_at(hour, minute), 31, 3, 7), 2024);
+            data.update_dst();
+            assert_eq!((data.cest, data.cet), (cest, !cest), "{:02}:{:02}", hour, minute);
         }
-        if self.year_in_century_tens & 8 != 0 {
-            value |= 1 << 57;
-            date_parity = !date_parity;
+
+        // Sunday, 2024-10-27: 02:30 keeps its zone, 03:00 is CET
+        let mut data = in_year(on_date(data_at(2, 30), 27, 10, 7), 2024);
+        data.update_dst();
+        assert!(data.cest);
+        let mut data = cet(data);
+        data.update_dst();
+        assert!(data.cet);
+        let mut data = in_year(on_date(data_at(3, 0), 27, 10, 7), 2024);
+        data.update_dst();
+        assert!(data.cet);
+
+        // a wrong zone is corrected at midnight
+        let mut data = cet(in_year(on_date(data_at(23, 59), 15, 7, 1), 2024));
+        data.increment_minute();
+        assert_eq!(data.day_of_month(), 16);
+        assert!(data.cest && !data.cet);
+    }
+
+    #[test]
+    fn test_update_dst_announcement() {
+        // Sunday, 2024-03-31: the frames transmitted from 01:00 to 01:59 CET announce the switch
+        let expected = [(0, 30, false), (1, 0, false), (1, 30, true), (3, 0, true), (3, 1, false)];
+        for (hour, minute, announced) in expected {
+            let mut data = in_year(on_date(data_at(hour, minute), 31, 3, 7), 2024);
+            data.summer_announcement = !announced;
+            data.update_dst();
+            assert_eq!(data.summer_announcement, announced, "{:02}:{:02}", hour, minute);
         }
 
-        // bit 58
-        if date_parity {
-            value |= 1 << 58;
+        // Sunday, 2024-03-24: no switch
+        let mut data = in_year(on_date(data_at(1, 30), 24, 3, 7), 2024);
+        data.summer_announcement = true;
+        data.update_dst();
+        assert!(!data.summer_announcement);
+    }
+
+    #[test]
+    fn test_ignore_summer_time() {
+        // Sunday, 2024-10-27, 02:58 CEST
+        let mut data = on_date(data_at(2, 58), 27, 10, 7);
+        data.follow_summer_time = false;
+        data.summer_announcement = true;
+        for _ in 0..3 {
+            data.increment_minute();
         }
+        assert_eq!((data.hour(), data.minute()), (3, 1));
+        assert!(data.cest);
+        assert!(!data.summer_announcement);
+    }
 
-        value
+    #[test]
+    fn test_dst_announcement_matches_timestamps() {
+        // stepping minute by minute agrees with converting each timestamp
+        for switch in [1_711_846_800, 1_729_990_800] {
+            let start = switch - 2 * 60 * 60;
+            let mut data = Dcf77Data::from_unix_timestamp(start);
+            for minute in 1..(4 * 60) {
+                data.increment_minute();
+                assert_eq!(data, Dcf77Data::from_unix_timestamp(start + minute * 60), "minute {}", minute);
+            }
+        }
     }
 }
@@ -4,34 +4,105 @@
 
 
 use core::fmt;
+use core::time::Duration;
 
 use atsaml21g18b::Peripherals;
 use atsaml21g18b::sercom0::I2CM;
 
 use crate::init::CORE_CLOCK_SPEED_HZ;
+use crate::sync_vcell::SyncVolatileCell;
+use crate::tick::{delay, delay_until};
 
 
-/// I<sup>2</sup>C speed in bits per second (SERCOM considers this equivalent to Hz).
+/// Default I<sup>2</sup>C speed in bits per second (SERCOM considers this equivalent to Hz).
 const I2C_SPEED_HZ: u32 = 100_000;
 
 
+/// The bits of a 10-bit address.
+const TEN_BIT_ADDRESS_MASK: u16 = 0b11_1111_1111;
+
+/// The fixed top bits of the first byte of a 10-bit address.
+const TEN_BIT_ADDRESS_PREFIX: u8 = 0b1111_0000;
+
+
+/// How long to wait for each step of releasing the bus when the controller is set up again.
+const RECOVERY_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// How long to wait for each step of a transfer before giving up with
+/// [`Timeout`](I2cErrorKind::Timeout).
+///
+/// A byte takes less than 100 µs at 100 kHz; the rest is leeway for peripherals that stretch the
+/// clock.
+const OPERATION_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The value of `STATUS.BUSSTATE` if we are the owner of the bus.
+const BUS_STATE_OWNER: u8 = 0b10;
+
+/// How long to wait before retrying a failed transmission; multiplied by the number of attempts
+/// made so far.
+const RETRY_BACKOFF: Duration = Duration::from_millis(1);
+
+
 const CMD_REPEATED_START: u8 = 0x1;
 const CMD_BYTE_READ: u8 = 0x2;
 const CMD_STOP: u8 = 0x3;
 
 
-const fn calculate_baud_divisor() -> u8 {
+/// The highest I<sup>2</sup>C speed in bits per second supported by standard and fast mode.
+pub(crate) const FAST_MODE_MAX_SPEED_HZ: u32 = 400_000;
+
+/// The highest I<sup>2</sup>C speed in bits per second supported by fast mode plus.
+const FAST_MODE_PLUS_MAX_SPEED_HZ: u32 = 1_000_000;
+
+
+/// Calculates the value of `BAUD` for the given I<sup>2</sup>C speed, or `None` if the speed cannot
+/// be reached with the core clock.
+const fn baud_divisor(speed_hz: u32) -> Option<u8> {
     // f_SCL = f_GCLK / (10 + 2*BAUD + f_GCLK * T_RISE)
     // datasheet table 46-12 mentions worst-case T_RISE = 13 ns = 13/1_000_000_000 s
 
-    // I2C_SPEED_HZ = CORE_CLOCK_SPEED_HZ / (10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s)
-    // I2C_SPEED_HZ * (10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s) = CORE_CLOCK_SPEED_HZ
-    // 10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s = CORE_CLOCK_SPEED_HZ / I2C_SPEED_HZ
-    // 10 + 2*BAUD = CORE_CLOCK_SPEED_HZ / I2C_SPEED_HZ - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s
-    // 2*BAUD = CORE_CLOCK_SPEED_HZ / I2C_SPEED_HZ - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s - 10
-    // BAUD = (CORE_CLOCK_SPEED_HZ / I2C_SPEED_HZ - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s - 10) / 2
+    // speed_hz = CORE_CLOCK_SPEED_HZ / (10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s)
+    // speed_hz * (10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s) = CORE_CLOCK_SPEED_HZ
+    // 10 + 2*BAUD + CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s = CORE_CLOCK_SPEED_HZ / speed_hz
+    // 10 + 2*BAUD = CORE_CLOCK_SPEED_HZ / speed_hz - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s
+    // 2*BAUD = CORE_CLOCK_SPEED_HZ / speed_hz - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s - 10
+    // BAUD = (CORE_CLOCK_SPEED_HZ / speed_hz - CORE_CLOCK_SPEED_HZ * 13/1_000_000_000 s - 10) / 2
+
+    if speed_hz == 0 {
+        return None;
+    }
+    let overhead = CORE_CLOCK_SPEED_HZ * 13 / 1_000_000_000 + 10;
+    let cycles = CORE_CLOCK_SPEED_HZ / speed_hz;
+    if cycles < overhead {
+        return None;
+    }
+    let baud = (cycles - overhead) / 2;
+    if baud > 0xFF {
+        return None;
+    }
+    Some(baud as u8)
+}
+
+const fn calculate_baud_divisor() -> u8 {
+    match baud_divisor(I2C_SPEED_HZ) {
+        Some(baud) => baud,
+        None => panic!("core clock too fast for the I2C speed; BAUD does not fit into 8 bits"),
+    }
+}
+
+const _: () = {
+    calculate_baud_divisor();
+};
 
-    ((CORE_CLOCK_SPEED_HZ / I2C_SPEED_HZ - CORE_CLOCK_SPEED_HZ * 13 / 1_000_000_000 - 10) / 2) as u8
+
+/// Returns the SCL frequency actually achieved with the given value of `BAUD`.
+///
+/// Since the divisor is an integer, this generally differs somewhat from the requested speed. The
+/// same worst-case rise time as in [`baud_divisor`] is assumed.
+const fn achieved_speed_hz(baud: u8) -> u32 {
+    // f_SCL = f_GCLK / (10 + 2*BAUD + f_GCLK * T_RISE), rounded to the nearest Hz
+    let divisor = 10 + 2 * (baud as u32) + CORE_CLOCK_SPEED_HZ * 13 / 1_000_000_000;
+    (CORE_CLOCK_SPEED_HZ + divisor / 2) / divisor
 }
 
 
@@ -52,8 +123,21 @@ pub enum I2cErrorKind {
 
     /// The given address is not a valid address.
     ///
-    /// This error is generally raised if the topmost bit is set.
+    /// This error is generally raised if the topmost bit of a 7-bit address or any of the topmost
+    /// six bits of a 10-bit address are set.
     InvalidAddress,
+
+    /// A step of the operation did not complete in time, e.g. because a peripheral holds SCL low.
+    ///
+    /// The controller is left in an unknown state; call
+    /// [`setup_controller`](SercomI2cController::setup_controller) to recover.
+    Timeout,
+
+    /// The peripheral device kept signalling that it is busy.
+    ///
+    /// Unlike [`Timeout`](I2cErrorKind::Timeout), the transfers themselves completed and the
+    /// controller is in a known state.
+    DeviceBusy,
 }
 impl I2cErrorKind {
     pub const fn to_error(&self, byte_info: I2cErrorByteInfo) -> I2cError {
@@ -63,10 +147,22 @@ impl I2cErrorKind {
         }
     }
 
+    /// Whether an operation that failed with this kind of error might succeed if it is retried.
+    pub const fn is_transient(&self) -> bool {
+        match self {
+            Self::ArbitrationLost | Self::NotAcknowledged | Self::DeviceBusy => true,
+            Self::BusError | Self::InvalidAddress | Self::Timeout => false,
+        }
+    }
+
     pub const fn at_address(&self, address: u8) -> I2cError {
         self.to_error(I2cErrorByteInfo::Address(address))
     }
 
+    pub const fn at_ten_bit_address(&self, address: u16) -> I2cError {
+        self.to_error(I2cErrorByteInfo::TenBitAddress(address))
+    }
+
     pub const fn at_data_index(&self, data: u8, index: usize) -> I2cError {
         self.to_error(I2cErrorByteInfo::Data {
             index,
@@ -89,6 +185,10 @@ impl fmt::Display for I2cErrorKind {
                 => write!(f, "byte not acknowledged"),
             Self::InvalidAddress
                 => write!(f, "invalid address"),
+            Self::Timeout
+                => write!(f, "timeout"),
+            Self::DeviceBusy
+                => write!(f, "device busy"),
         }
     }
 }
@@ -100,6 +200,9 @@ pub enum I2cErrorByteInfo {
     /// The address byte (includes the read/write flag).
     Address(u8),
 
+    /// The 10-bit address (both address bytes, including the read/write flag).
+    TenBitAddress(u16),
+
     /// The data byte at the given index.
     Data { index: usize, byte: u8 },
 
@@ -111,6 +214,7 @@ impl I2cErrorByteInfo {
     pub fn is_address(&self) -> bool {
         match self {
             Self::Address(_) => true,
+            Self::TenBitAddress(_) => true,
             _ => false,
         }
     }
@@ -120,6 +224,8 @@ impl fmt::Display for I2cErrorByteInfo {
         match self {
             Self::Address(address)
                 => write!(f, "address byte 0b{:07b}", address),
+            Self::TenBitAddress(address)
+                => write!(f, "10-bit address 0b{:010b}", address),
             Self::Data { index, byte }
                 => write!(f, "data byte {0} (0x{0:02X}) at index {1} (0x{1:X})", byte, index),
             Self::StopBit
@@ -153,12 +259,103 @@ pub(crate) trait SercomI2cController {
     /// Obtains a reference to the SERCOM register block.
     fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::sercom0::I2CM;
 
+    /// Obtains the cell storing the speed at which the controller is set up, initially
+    /// [`I2C_SPEED_HZ`].
+    fn speed_cell() -> &'static SyncVolatileCell<u32>;
+
+    /// The SDA hold time relative to the negative edge of SCL, as programmed into `CTRLA.SDAHOLD`.
+    ///
+    /// | value | hold time  |
+    /// | ----- | ---------- |
+    /// | 0x0   | none       |
+    /// | 0x1   | 50-100 ns  |
+    /// | 0x2   | 300-600 ns |
+    /// | 0x3   | 400-800 ns |
+    ///
+    /// A longer hold time helps peripherals on long traces or with slowly rising SDA that would
+    /// otherwise sporadically fail to acknowledge. Defaults to no hold time.
+    fn sda_hold() -> u8 {
+        0x0
+    }
+
     /// Sets up the SERCOM device as an I<sup>2</sup>C controller.
+    ///
+    /// This function may be called again at any time to recover from an error such as
+    /// [`BusError`](I2cErrorKind::BusError), [`ArbitrationLost`](I2cErrorKind::ArbitrationLost) or
+    /// [`Timeout`](I2cErrorKind::Timeout); it is the sanctioned way of returning the controller to
+    /// a known state. If the controller is already enabled, a pending operation is given some time
+    /// to complete, the bus is released if we own it, and all flags are cleared before the device
+    /// is reset.
+    ///
+    /// The bus runs at the speed most recently passed to
+    /// [`setup_controller_with_speed`](SercomI2cController::setup_controller_with_speed), or at
+    /// [`I2C_SPEED_HZ`] if that function has not been called yet.
+    ///
+    /// The tick clock must be running.
     fn setup_controller(peripherals: &mut Peripherals) {
+        Self::setup_controller_with_speed(peripherals, Self::speed_cell().get());
+    }
+
+    /// Sets up the SERCOM device as an I<sup>2</sup>C controller running at the given speed in bits
+    /// per second.
+    ///
+    /// Apart from the speed, this is the same as
+    /// [`setup_controller`](SercomI2cController::setup_controller). The speed is remembered, so
+    /// calling [`setup_controller`](SercomI2cController::setup_controller) later to recover from an
+    /// error keeps it. Speeds above 400 kHz use fast mode plus, which not all peripherals support.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `speed_hz` is 0, above 1 MHz, or too low to be reached with the core clock.
+    fn setup_controller_with_speed(peripherals: &mut Peripherals, speed_hz: u32) {
+        assert!(speed_hz <= FAST_MODE_PLUS_MAX_SPEED_HZ, "I2C speed too high");
+        let baud = baud_divisor(speed_hz)
+            .expect("I2C speed cannot be reached with the core clock");
+        let speed_mode = if speed_hz > FAST_MODE_MAX_SPEED_HZ { 0x1 } else { 0x0 };
+        Self::speed_cell().set(speed_hz);
+
         Self::enable_clock(peripherals);
 
         let register_block = Self::get_register_block(peripherals);
 
+        if register_block.ctrla.read().enable().bit_is_set() {
+            // wait for a pending operation to complete (but don't hang if it never does)
+            delay_until(RECOVERY_TIMEOUT, || register_block.syncbusy.read().sysop().bit_is_clear());
+
+            // release the bus if we own it
+            if register_block.status.read().busstate().bits() == BUS_STATE_OWNER {
+                register_block.ctrlb.modify(|_, w| w
+                    .cmd().variant(CMD_STOP)
+                );
+                delay_until(RECOVERY_TIMEOUT, || register_block.syncbusy.read().sysop().bit_is_clear());
+            }
+
+            // clear all flags
+            unsafe {
+                register_block.intflag.write_with_zero(|w| w
+                    .mb().set_bit()
+                    .sb().set_bit()
+                    .error().set_bit()
+                )
+            };
+            unsafe {
+                register_block.status.write_with_zero(|w| w
+                    .buserr().set_bit()
+                    .arblost().set_bit()
+                    .lowtout().set_bit()
+                    .mexttout().set_bit()
+                    .sexttout().set_bit()
+                    .lenerr().set_bit()
+                )
+            };
+
+            // disable the controller
+            register_block.ctrla.modify(|_, w| w
+                .enable().clear_bit()
+            );
+            delay_until(RECOVERY_TIMEOUT, || register_block.syncbusy.read().enable().bit_is_clear());
+        }
+
         // reset SERCOM
         register_block.ctrla.modify(|_, w| w
             .swrst().set_bit()
@@ -170,19 +367,19 @@ pub(crate) trait SercomI2cController {
         register_block.ctrla.modify(|_, w| w
             .mode().variant(0x5) // I2C controller
             .pinout().clear_bit() // disable 4-bit mode
-            .sdahold().variant(0) // no SDA hold time relative to the negative edge
+            .sdahold().variant(Self::sda_hold()) // SDA hold time relative to the negative edge
             .mexttoen().clear_bit() // no controller SCL-low-extend timeout
             .sexttoen().clear_bit() // no peripheral SCL-low-extend timeout
-            .speed().variant(0) // standard speed (100 kHz)
+            .speed().variant(speed_mode) // standard or fast mode (up to 400 kHz) or fast mode plus
             .sclsm().clear_bit() // regular SCL clock-stretch mode
             .lowtouten().clear_bit() // no SCL-low timeout
         );
         register_block.ctrlb.modify(|_, w| w
             .smen().clear_bit() // no smart mode
-            .qcen().clear_bit() // no quick command
+            .qcen().clear_bit() // no quick command (enabled temporarily by quick_command)
         );
         register_block.baud.modify(|_, w| w
-            .baud().variant(calculate_baud_divisor())
+            .baud().variant(baud)
             .baudlow().variant(0) // use BAUD for BAUDLOW
         );
 
@@ -193,19 +390,34 @@ pub(crate) trait SercomI2cController {
         while register_block.syncbusy.read().enable().bit_is_set() {
         }
 
-        // grab the bus
+        // grab the bus (if this does not complete, the next transfer times out)
         register_block.status.modify(|_, w| w
             .busstate().variant(0b01)
         );
-        while register_block.syncbusy.read().sysop().bit_is_set() {
-        }
+        delay_until(RECOVERY_TIMEOUT, || register_block.syncbusy.read().sysop().bit_is_clear());
     }
 
-    /// Waits until a byte is transmitted, then checks the current bus status and returns the
-    /// corresponding error if one has occurred.
-    fn wait_and_check_bus_status(register_block: &I2CM, byte_info: I2cErrorByteInfo) -> Result<(), I2cError> {
+    /// Returns the SCL frequency actually achieved at the speed the controller is set up with.
+    ///
+    /// Since `BAUD` is an integer, this generally differs somewhat from the requested speed.
+    fn actual_speed_hz() -> u32 {
+        // speed_cell only ever contains speeds accepted by setup_controller_with_speed
+        let baud = baud_divisor(Self::speed_cell().get())
+            .unwrap_or(calculate_baud_divisor());
+        achieved_speed_hz(baud)
+    }
+
+    /// Waits until a byte (address or data) is transmitted, then checks the current bus status and
+    /// returns the corresponding error if one has occurred.
+    ///
+    /// This is only meaningful when the peripheral is receiving, i.e. for addresses in the write
+    /// direction and written data; use [`wait_and_check_read_status`] when reading.
+    ///
+    /// [`wait_and_check_read_status`]: SercomI2cController::wait_and_check_read_status
+    fn wait_and_check_write_status(register_block: &I2CM, byte_info: I2cErrorByteInfo) -> Result<(), I2cError> {
         // wait until our controller status is known, then clear that bit
-        while register_block.intflag.read().mb().bit_is_clear() {
+        if !delay_until(OPERATION_TIMEOUT, || register_block.intflag.read().mb().bit_is_set()) {
+            return Err(I2cErrorKind::Timeout.to_error(byte_info));
         }
         unsafe {
             register_block.intflag.write_with_zero(|w| w
@@ -213,11 +425,72 @@ pub(crate) trait SercomI2cController {
             )
         };
 
-        let bus_status = register_block.status.read();
         // everything OK = MB
         // arbitration lost = MB | ARBLOST
         // bus error = MB | ARBLOST | BUSERR
         // (but MB is no longer set)
+        Self::check_bus_errors(register_block, byte_info)?;
+
+        // maybe the transmission succeeded but nobody responded (RXNACK is set on NACK)
+        if register_block.status.read().rxnack().bit_is_set() {
+            return Err(I2cErrorKind::NotAcknowledged.to_error(byte_info));
+        }
+
+        Ok(())
+    }
+
+    /// Waits until a byte has been received from the peripheral, then checks the current bus status
+    /// and returns the corresponding error if one has occurred.
+    ///
+    /// When reading, the controller acknowledges the data itself, so RXNACK says nothing about the
+    /// peripheral; a received byte sets SB. MB is only set if something went wrong: the address in
+    /// the read direction was not acknowledged, arbitration was lost or a bus error occurred.
+    fn wait_and_check_read_status(register_block: &I2CM, byte_info: I2cErrorByteInfo) -> Result<(), I2cError> {
+        // wait for either flag
+        let done = delay_until(OPERATION_TIMEOUT, || {
+            let flags = register_block.intflag.read();
+            flags.mb().bit_is_set() || flags.sb().bit_is_set()
+        });
+        if !done {
+            return Err(I2cErrorKind::Timeout.to_error(byte_info));
+        }
+
+        if register_block.intflag.read().sb().bit_is_set() {
+            unsafe {
+                register_block.intflag.write_with_zero(|w| w
+                    .sb().set_bit()
+                )
+            };
+            return Ok(());
+        }
+        unsafe {
+            register_block.intflag.write_with_zero(|w| w
+                .mb().set_bit()
+            )
+        };
+
+        Self::check_bus_errors(register_block, byte_info)?;
+
+        // no bus error => the address was not acknowledged
+        Err(I2cErrorKind::NotAcknowledged.to_error(byte_info))
+    }
+
+    /// Waits until the current bus operation has been synchronized.
+    ///
+    /// Returns a [`Timeout`](I2cErrorKind::Timeout) error for the given byte if this takes longer
+    /// than [`OPERATION_TIMEOUT`], e.g. because a peripheral holds SCL low.
+    fn wait_for_sysop(register_block: &I2CM, byte_info: I2cErrorByteInfo) -> Result<(), I2cError> {
+        if delay_until(OPERATION_TIMEOUT, || register_block.syncbusy.read().sysop().bit_is_clear()) {
+            Ok(())
+        } else {
+            Err(I2cErrorKind::Timeout.to_error(byte_info))
+        }
+    }
+
+    /// Checks whether a bus error has occurred or arbitration has been lost, returning and clearing
+    /// the corresponding error if so.
+    fn check_bus_errors(register_block: &I2CM, byte_info: I2cErrorByteInfo) -> Result<(), I2cError> {
+        let bus_status = register_block.status.read();
         if bus_status.buserr().bit_is_set() {
             unsafe {
                 register_block.status.write_with_zero(|w| w
@@ -235,15 +508,86 @@ pub(crate) trait SercomI2cController {
             };
             return Err(I2cErrorKind::ArbitrationLost.to_error(byte_info));
         }
+        Ok(())
+    }
 
-        // maybe the transmission succeeded but nobody responded
-        if bus_status.rxnack().bit_is_clear() {
-            return Err(I2cErrorKind::NotAcknowledged.to_error(byte_info));
+    /// Writes the given data without releasing the bus.
+    ///
+    /// The address must already have been transmitted.
+    fn write_data<I: IntoIterator<Item = u8>>(register_block: &I2CM, data: I) -> Result<(), I2cError> {
+        let mut bytes_written = 0;
+        for byte in data {
+            // send
+            register_block.data.modify(|_, w| w
+                .data().variant(byte)
+            );
+            Self::wait_for_sysop(register_block, I2cErrorByteInfo::Data { index: bytes_written, byte })?;
+            Self::wait_and_check_write_status(register_block, I2cErrorByteInfo::Data { index: bytes_written, byte })?;
+            bytes_written += 1;
         }
-
         Ok(())
     }
 
+    /// Writes the given data, then sends STOP.
+    ///
+    /// The address must already have been transmitted.
+    fn write_data_and_stop<I: IntoIterator<Item = u8>>(register_block: &I2CM, data: I) -> Result<(), I2cError> {
+        Self::write_data(register_block, data)?;
+
+        // send STOP
+        register_block.ctrlb.modify(|_, w| w
+            .cmd().variant(CMD_STOP)
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::StopBit)?;
+
+        // no byte is transferred after STOP, so MB is never set again; only the bus errors are left
+        // to check (as when reading)
+        Self::check_bus_errors(register_block, I2cErrorByteInfo::StopBit)
+    }
+
+    /// Reads data, passing each byte to `handle_byte`, until it returns `false`; then sends STOP.
+    ///
+    /// Each byte for which `handle_byte` returns `true` is acknowledged, requesting another byte; the
+    /// final byte is not acknowledged, telling the peripheral to stop sending, and followed by STOP.
+    ///
+    /// The address must already have been transmitted and the first byte received (see
+    /// [`wait_and_check_read_status`](SercomI2cController::wait_and_check_read_status)). An error
+    /// while receiving a subsequent byte is reported for the byte before it.
+    fn read_data_and_stop<F: FnMut(u8) -> bool>(register_block: &I2CM, mut handle_byte: F) -> Result<(), I2cError> {
+        let mut bytes_read = 0;
+        loop {
+            // receive
+            let byte = register_block.data.read().data().bits();
+            let byte_info = I2cErrorByteInfo::Data { byte, index: bytes_read };
+            bytes_read += 1;
+
+            // the acknowledge action is carried out when the command is written: the controller
+            // first sends ACK or NACK for the byte just received and then executes the command
+            let acknowledge = handle_byte(byte);
+            if acknowledge {
+                // send ACK and read again
+                register_block.ctrlb.modify(|_, w| w
+                    .ackact().clear_bit() // ACK
+                    .cmd().variant(CMD_BYTE_READ)
+                );
+                Self::wait_for_sysop(register_block, byte_info)?;
+                Self::wait_and_check_read_status(register_block, byte_info)?;
+            } else {
+                // send NACK, then STOP
+                register_block.ctrlb.modify(|_, w| w
+                    .ackact().set_bit() // NACK
+                    .cmd().variant(CMD_STOP)
+                );
+                Self::wait_for_sysop(register_block, I2cErrorByteInfo::StopBit)?;
+                break;
+            }
+        }
+
+        // STOP does not set MB and nobody acknowledges anything after a read; only check that the
+        // bus did not fail while we were releasing it
+        Self::check_bus_errors(register_block, I2cErrorByteInfo::StopBit)
+    }
+
     /// Sends data to a peripheral device.
     fn send<I: IntoIterator<Item = u8>>(peripherals: &mut Peripherals, address: u8, data: I) -> Result<(), I2cError> {
         if address & 0b1000_0000 != 0 {
@@ -260,35 +604,120 @@ pub(crate) trait SercomI2cController {
             .hs().clear_bit() // no high-speed transfer
             .tenbiten().clear_bit() // disable 10-bit addressing
         );
-        while register_block.syncbusy.read().sysop().bit_is_set() {
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+
+        Self::wait_and_check_write_status(register_block, I2cErrorByteInfo::Address(address))?;
+
+        Self::write_data_and_stop(register_block, data)
+    }
+
+    /// Performs a quick command: transmits the address with the given direction bit, then sends STOP
+    /// without transferring any data.
+    ///
+    /// Returns `Ok(())` if a peripheral acknowledged the address. This is the cleanest way of probing
+    /// for a device, and some SMBus devices interpret the direction bit itself as a command.
+    fn quick_command(peripherals: &mut Peripherals, address: u8, read: bool) -> Result<(), I2cError> {
+        if address & 0b1000_0000 != 0 {
+            return Err(I2cErrorKind::InvalidAddress.at_address(address));
         }
 
-        Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::Address(address))?;
+        let register_block = Self::get_register_block(peripherals);
 
-        // write data
-        let mut bytes_written = 0;
-        for byte in data {
-            // send
-            register_block.data.modify(|_, w| w
-                .data().variant(byte)
-            );
-            while register_block.syncbusy.read().sysop().bit_is_set() {
-            }
-            Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::Data { index: bytes_written, byte })?;
-            bytes_written += 1;
+        // have the controller send STOP right after the address
+        register_block.ctrlb.modify(|_, w| w
+            .qcen().set_bit()
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+
+        // set address
+        let address_and_direction: u8 = (address << 1) | if read { 0b1 } else { 0b0 };
+        register_block.addr.modify(|_, w| w
+            .addr().variant(address_and_direction.into())
+            .lenen().clear_bit() // no DMA
+            .hs().clear_bit() // no high-speed transfer
+            .tenbiten().clear_bit() // disable 10-bit addressing
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+
+        // a read sets SB instead of MB; wait for either, then clear both
+        let done = delay_until(OPERATION_TIMEOUT, || {
+            let flags = register_block.intflag.read();
+            flags.mb().bit_is_set() || flags.sb().bit_is_set()
+        });
+        if !done {
+            return Err(I2cErrorKind::Timeout.at_address(address));
         }
+        unsafe {
+            register_block.intflag.write_with_zero(|w| w
+                .mb().set_bit()
+                .sb().set_bit()
+            )
+        };
 
-        // send STOP
+        let bus_status = register_block.status.read();
+        let result = if bus_status.buserr().bit_is_set() {
+            Err(I2cErrorKind::BusError.at_address(address))
+        } else if bus_status.arblost().bit_is_set() {
+            Err(I2cErrorKind::ArbitrationLost.at_address(address))
+        } else if bus_status.rxnack().bit_is_set() {
+            // RXNACK is set if the address was not acknowledged
+            Err(I2cErrorKind::NotAcknowledged.at_address(address))
+        } else {
+            Ok(())
+        };
+        unsafe {
+            register_block.status.write_with_zero(|w| w
+                .buserr().set_bit()
+                .arblost().set_bit()
+            )
+        };
+
+        // back to regular transactions
         register_block.ctrlb.modify(|_, w| w
-            .cmd().variant(CMD_STOP)
+            .qcen().clear_bit()
         );
-        while register_block.syncbusy.read().sysop().bit_is_set() {
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+
+        result
+    }
+
+    /// Probes every non-reserved 7-bit address (0x08 through 0x77) with a
+    /// [`quick_command`](SercomI2cController::quick_command) in the write direction, calling
+    /// `on_found` with each address that is acknowledged.
+    ///
+    /// Stops at the first error other than [`NotAcknowledged`](I2cErrorKind::NotAcknowledged) and
+    /// returns it; `on_found` has been called for the addresses found up to that point.
+    fn scan<F: FnMut(u8)>(peripherals: &mut Peripherals, mut on_found: F) -> Result<(), I2cError> {
+        for address in 0x08..=0x77 {
+            match Self::quick_command(peripherals, address, false) {
+                Ok(()) => on_found(address),
+                Err(e) if e.kind == I2cErrorKind::NotAcknowledged => {},
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends data to a peripheral device, retrying up to `attempts` times in total if a transient
+    /// error occurs (see [`I2cErrorKind::is_transient`]).
+    ///
+    /// The wait between attempts grows with each attempt. Returns the last error if all attempts
+    /// fail. If `attempts` is 0, a single attempt is made nonetheless.
+    fn send_retry(peripherals: &mut Peripherals, address: u8, data: &[u8], attempts: u8) -> Result<(), I2cError> {
+        let mut attempt = 1;
+        loop {
+            match Self::send(peripherals, address, data.iter().copied()) {
+                Err(e) if e.kind.is_transient() && attempt < attempts => {
+                    delay(RETRY_BACKOFF * u32::from(attempt));
+                    attempt += 1;
+                },
+                other => return other,
+            }
         }
-        Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::StopBit)
     }
 
     /// Receives data from a peripheral device.
-    fn receive<F: FnMut(u8) -> bool>(peripherals: &mut Peripherals, address: u8, mut handle_byte: F) -> Result<(), I2cError> {
+    fn receive<F: FnMut(u8) -> bool>(peripherals: &mut Peripherals, address: u8, handle_byte: F) -> Result<(), I2cError> {
         if address & 0b1000_0000 != 0 {
             return Err(I2cErrorKind::InvalidAddress.at_address(address));
         }
@@ -303,39 +732,124 @@ pub(crate) trait SercomI2cController {
             .hs().clear_bit() // no high-speed transfer
             .tenbiten().clear_bit() // disable 10-bit addressing
         );
-        while register_block.syncbusy.read().sysop().bit_is_set() {
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+        Self::wait_and_check_read_status(register_block, I2cErrorByteInfo::Address(address))?;
+
+        Self::read_data_and_stop(register_block, handle_byte)
+    }
+
+    /// Sends data to a peripheral device, then receives data from it after a repeated START.
+    ///
+    /// The bus is not released between sending and receiving, so no other controller can interfere.
+    /// Received bytes are passed to `handle_byte` as with [`receive`](SercomI2cController::receive).
+    fn write_read<I: IntoIterator<Item = u8>, F: FnMut(u8) -> bool>(
+        peripherals: &mut Peripherals,
+        address: u8,
+        data: I,
+        handle_byte: F,
+    ) -> Result<(), I2cError> {
+        if address & 0b1000_0000 != 0 {
+            return Err(I2cErrorKind::InvalidAddress.at_address(address));
         }
-        Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::Address(address))?;
 
-        // read data
-        let mut bytes_read = 0;
-        loop {
-            // receive
-            let byte = register_block.data.read().data().bits();
-            Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::Data { byte, index: bytes_read })?;
-            bytes_read += 1;
+        let register_block = Self::get_register_block(peripherals);
 
-            let acknowledge = handle_byte(byte);
-            if acknowledge {
-                // send acknowledge bit and read again
-                register_block.ctrlb.modify(|_, w| w
-                    .ackact().set_bit()
-                    .cmd().variant(CMD_BYTE_READ)
-                );
-                while register_block.syncbusy.read().sysop().bit_is_set() {
-                }
-            } else {
-                // don't acknowledge and send STOP
-                register_block.ctrlb.modify(|_, w| w
-                    .ackact().clear_bit()
-                    .cmd().variant(CMD_STOP)
-                );
-                while register_block.syncbusy.read().sysop().bit_is_set() {
-                }
-                break;
-            }
+        // set address
+        let address_and_write: u8 = address << 1;
+        register_block.addr.modify(|_, w| w
+            .addr().variant(address_and_write.into())
+            .lenen().clear_bit() // no DMA
+            .hs().clear_bit() // no high-speed transfer
+            .tenbiten().clear_bit() // disable 10-bit addressing
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+        Self::wait_and_check_write_status(register_block, I2cErrorByteInfo::Address(address))?;
+
+        Self::write_data(register_block, data)?;
+
+        // setting the address while we own the bus sends a repeated START
+        let address_and_read: u8 = (address << 1) | 0b1;
+        register_block.addr.modify(|_, w| w
+            .addr().variant(address_and_read.into())
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::Address(address))?;
+        Self::wait_and_check_read_status(register_block, I2cErrorByteInfo::Address(address))?;
+
+        Self::read_data_and_stop(register_block, handle_byte)
+    }
+
+    /// Reads consecutive registers of a peripheral device, starting at `register`, into `buffer`.
+    ///
+    /// This is the common pattern of writing the register number, then reading as many bytes as
+    /// required after a repeated START. If `buffer` is empty, only the register number is written.
+    fn read_register(peripherals: &mut Peripherals, address: u8, register: u8, buffer: &mut [u8]) -> Result<(), I2cError> {
+        if buffer.is_empty() {
+            return Self::send(peripherals, address, [register]);
         }
-        Self::wait_and_check_bus_status(register_block, I2cErrorByteInfo::StopBit)
+
+        let mut index = 0;
+        Self::write_read(peripherals, address, [register], |byte| {
+            buffer[index] = byte;
+            index += 1;
+
+            // acknowledge (and continue reading) until the buffer is full
+            index < buffer.len()
+        })
+    }
+
+    /// Sends data to a peripheral device with a 10-bit address.
+    fn send_10bit<I: IntoIterator<Item = u8>>(peripherals: &mut Peripherals, address: u16, data: I) -> Result<(), I2cError> {
+        if address & !TEN_BIT_ADDRESS_MASK != 0 {
+            return Err(I2cErrorKind::InvalidAddress.at_ten_bit_address(address));
+        }
+
+        let register_block = Self::get_register_block(peripherals);
+
+        // set address (the controller sends both address bytes)
+        let address_and_write: u16 = address << 1;
+        register_block.addr.modify(|_, w| w
+            .addr().variant(address_and_write)
+            .lenen().clear_bit() // no DMA
+            .hs().clear_bit() // no high-speed transfer
+            .tenbiten().set_bit() // enable 10-bit addressing
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+
+        Self::wait_and_check_write_status(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+
+        Self::write_data_and_stop(register_block, data)
+    }
+
+    /// Receives data from a peripheral device with a 10-bit address.
+    fn receive_10bit<F: FnMut(u8) -> bool>(peripherals: &mut Peripherals, address: u16, handle_byte: F) -> Result<(), I2cError> {
+        if address & !TEN_BIT_ADDRESS_MASK != 0 {
+            return Err(I2cErrorKind::InvalidAddress.at_ten_bit_address(address));
+        }
+
+        let register_block = Self::get_register_block(peripherals);
+
+        // a 10-bit read starts out as a write of both address bytes...
+        let address_and_write: u16 = address << 1;
+        register_block.addr.modify(|_, w| w
+            .addr().variant(address_and_write)
+            .lenen().clear_bit() // no DMA
+            .hs().clear_bit() // no high-speed transfer
+            .tenbiten().set_bit() // enable 10-bit addressing
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+        Self::wait_and_check_write_status(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+
+        // ...followed by a repeated START with only the first address byte in the read direction
+        // (as described in the I2C chapter of the SAM L21 datasheet)
+        let first_byte_and_read: u8 = TEN_BIT_ADDRESS_PREFIX | (((address >> 8) as u8) << 1) | 0b1;
+        register_block.addr.modify(|_, w| w
+            .addr().variant(first_byte_and_read.into())
+            .tenbiten().clear_bit() // only send the first address byte
+        );
+        Self::wait_for_sysop(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+        Self::wait_and_check_read_status(register_block, I2cErrorByteInfo::TenBitAddress(address))?;
+
+        Self::read_data_and_stop(register_block, handle_byte)
     }
 }
 
@@ -360,6 +874,11 @@ impl SercomI2cController for Sercom0I2cController {
     fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::sercom0::I2CM {
         unsafe { (&*atsaml21g18b::SERCOM0::PTR).i2cm() }
     }
+
+    fn speed_cell() -> &'static SyncVolatileCell<u32> {
+        static SPEED_HZ: SyncVolatileCell<u32> = SyncVolatileCell::new(I2C_SPEED_HZ);
+        &SPEED_HZ
+    }
 }
 
 pub(crate) struct Sercom1I2cController;
@@ -382,4 +901,9 @@ impl SercomI2cController for Sercom1I2cController {
     fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::sercom0::I2CM {
         unsafe { (&*atsaml21g18b::SERCOM1::PTR).i2cm() }
     }
+
+    fn speed_cell() -> &'static SyncVolatileCell<u32> {
+        static SPEED_HZ: SyncVolatileCell<u32> = SyncVolatileCell::new(I2C_SPEED_HZ);
+        &SPEED_HZ
+    }
 }
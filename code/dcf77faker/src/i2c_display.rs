@@ -1,14 +1,85 @@
+use core::fmt;
+use core::marker::PhantomData;
 use core::time::Duration;
 
 use atsaml21g18b::Peripherals;
+use dcf77faker::transmitter::DisplaySink;
 
-use crate::i2c_controller::{I2cError, Sercom0I2cController, SercomI2cController};
+use crate::i2c_controller::{I2cError, I2cErrorKind, Sercom0I2cController, SercomI2cController};
 use crate::tick::delay;
 
 
 const LONG_DELAY: Duration = Duration::from_micros(2_160);
 const SHORT_DELAY: Duration = Duration::from_nanos(52_600);
 
+/// How many times to try sending a byte to the display before giving up.
+///
+/// Each byte only sets the state of the GPIO pins, so sending it again is harmless.
+const SEND_ATTEMPTS: u8 = 3;
+
+/// How many times to poll the busy flag of the display before giving up.
+///
+/// Each poll takes several I2C transfers, so this is far longer than the slowest instruction
+/// (nominally 1.52ms) takes to execute.
+const BUSY_POLL_ATTEMPTS: u16 = 100;
+
+/// How long to wait before repeating a failed display setup.
+///
+/// The HD44780 needs up to 40ms after the supply voltage has risen before it accepts instructions.
+const SETUP_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The default character output in place of a character that the display cannot show.
+const NON_ASCII_PLACEHOLDER: u8 = b'?';
+
+
+/// Converts a character into the corresponding code of the HD44780 character ROM A00 (the
+/// Japanese variant), or `fallback` if the ROM does not contain that character.
+///
+/// The ROM largely matches ASCII, except that it contains a yen sign instead of a backslash and
+/// arrows instead of a tilde and DEL; a few non-ASCII characters are found in the upper half.
+const fn to_rom_a00(c: char, fallback: u8) -> u8 {
+    match c {
+        '\\' | '~' => fallback,
+        ' '..='}' => c as u8,
+        '\u{A5}' => 0x5C, // YEN SIGN
+        '\u{B0}' => 0xDF, // DEGREE SIGN
+        '\u{E4}' => 0xE1, // LATIN SMALL LETTER A WITH DIAERESIS
+        '\u{DF}' => 0xE2, // LATIN SMALL LETTER SHARP S (shown as a beta)
+        '\u{B5}' => 0xE4, // MICRO SIGN
+        '\u{F6}' => 0xEF, // LATIN SMALL LETTER O WITH DIAERESIS
+        '\u{FC}' => 0xF5, // LATIN SMALL LETTER U WITH DIAERESIS
+        _ => fallback,
+    }
+}
+
+
+/// The font of an HD44780 display.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Font {
+    /// Characters of 5x8 dots (the last row is usually taken up by the cursor).
+    Dots5x8,
+
+    /// Characters of 5x10 dots; only available on displays with a single line.
+    Dots5x10,
+}
+
+
+/// One of the eight characters whose dot pattern can be defined in the character generator RAM (see
+/// [`define_custom_char`](I2cDisplay::define_custom_char)).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct CustomChar(u8);
+impl CustomChar {
+    /// All custom characters, ordered by their codes.
+    pub(crate) const ALL: [Self; 8] = [
+        Self(0), Self(1), Self(2), Self(3), Self(4), Self(5), Self(6), Self(7),
+    ];
+
+    /// The code with which the character is output using [`write_text`](I2cDisplay::write_text).
+    pub(crate) const fn code(&self) -> u8 {
+        self.0
+    }
+}
+
 
 /// Common trait for I2C character-based liquid crystal displays consisting of:
 ///
@@ -37,6 +108,30 @@ pub(crate) trait I2cDisplay<T: SercomI2cController> {
     /// Changes whether the user wants the backlight of the display turned on.
     fn set_wants_backlight(&mut self, wants_backlight: bool);
 
+    /// Whether the backlight is lit at this moment.
+    ///
+    /// This differs from [`wants_backlight`](I2cDisplay::wants_backlight) if the display dims the
+    /// backlight by switching it on and off.
+    fn backlight_lit(&self) -> bool {
+        self.wants_backlight()
+    }
+
+    /// Obtains the number of columns and rows of the display.
+    fn geometry(&self) -> (u8, u8);
+
+    /// The character output in place of a character that the display cannot show.
+    fn fallback_char(&self) -> u8 {
+        NON_ASCII_PLACEHOLDER
+    }
+
+    /// The font of the display.
+    ///
+    /// [`Font::Dots5x10`] is only honored on displays with a single row; the HD44780 cannot show
+    /// it on two lines.
+    fn font(&self) -> Font {
+        Font::Dots5x8
+    }
+
     /// Transmits a nibble (4 bits) of data.
     fn transmit_nibble(&self, peripherals: &mut Peripherals, nibble: u8, rs: bool) -> Result<(), I2cError> {
         // pin mapping (bits 7 to 0):
@@ -47,26 +142,26 @@ pub(crate) trait I2cDisplay<T: SercomI2cController> {
         // RS = Register Select (0 for command, 1 for data)
 
         // prepare the byte to transmit, with E low
-        let backlight_flag = if self.wants_backlight() { 0b0000_1000 } else { 0b0000_0000 };
+        let backlight_flag = if self.backlight_lit() { 0b0000_1000 } else { 0b0000_0000 };
         let rs_flag = if rs { 0b0000_0001 } else { 0b0000_0000 };
         let mut transmit_me = (nibble << 4) | backlight_flag | rs_flag;
 
         // send (with E low)
-        T::send(peripherals, self.display_address(), [transmit_me])?;
+        T::send_retry(peripherals, self.display_address(), &[transmit_me], SEND_ATTEMPTS)?;
         delay(Duration::from_nanos(500));
 
         // pull E high
         transmit_me |= 0b0000_0100;
 
         // send (with E high)
-        T::send(peripherals, self.display_address(), [transmit_me])?;
+        T::send_retry(peripherals, self.display_address(), &[transmit_me], SEND_ATTEMPTS)?;
         delay(Duration::from_nanos(500));
 
         // pull E low
         transmit_me &= 0b1111_1011;
 
         // send (with E low)
-        T::send(peripherals, self.display_address(), [transmit_me])?;
+        T::send_retry(peripherals, self.display_address(), &[transmit_me], SEND_ATTEMPTS)?;
         delay(Duration::from_nanos(500));
 
         Ok(())
@@ -95,12 +190,50 @@ pub(crate) trait I2cDisplay<T: SercomI2cController> {
         delay(LONG_DELAY);
     }
 
+    /// Waits until the display controller has finished executing the previous instruction.
+    ///
+    /// This reads the busy flag through R/~W instead of waiting for the worst-case execution time.
+    /// The busy flag can only be read once the display has been switched to 4-bit mode; until then,
+    /// [`basic_setup`](I2cDisplay::basic_setup) waits for fixed delays.
+    ///
+    /// Returns a [`DeviceBusy`](I2cErrorKind::DeviceBusy) error if the display is still busy after
+    /// [`BUSY_POLL_ATTEMPTS`] polls.
+    fn wait_busy(&self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
+        // D7 to D4 must be high so that the display can pull them low; RS = 0 (busy flag and
+        // address counter), RW = 1 (read)
+        let backlight_flag = if self.backlight_lit() { 0b0000_1000 } else { 0b0000_0000 };
+        let e_low = 0b1111_0000 | backlight_flag | 0b0000_0010;
+        let e_high = e_low | 0b0000_0100;
+
+        for _ in 0..BUSY_POLL_ATTEMPTS {
+            // upper nibble: busy flag (D7) and the upper bits of the address counter
+            T::send_retry(peripherals, self.display_address(), &[e_high], SEND_ATTEMPTS)?;
+            let mut upper = 0;
+            T::receive(peripherals, self.display_address(), |b| {
+                upper = b;
+                false
+            })?;
+            T::send_retry(peripherals, self.display_address(), &[e_low], SEND_ATTEMPTS)?;
+
+            // lower nibble: not needed, but it must be clocked out to finish the read
+            T::send_retry(peripherals, self.display_address(), &[e_high], SEND_ATTEMPTS)?;
+            T::send_retry(peripherals, self.display_address(), &[e_low], SEND_ATTEMPTS)?;
+
+            if upper & 0b1000_0000 == 0 {
+                return Ok(());
+            }
+        }
+        Err(I2cErrorKind::DeviceBusy.at_address(self.display_address()))
+    }
+
     /// Updates the backlight status for the display.
+    ///
+    /// The backlight is switched according to [`backlight_lit`](I2cDisplay::backlight_lit).
     fn update_backlight(&self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
         // as long as we keep E low, the display controller ignores us
         // => simply transmit all low bits except for the backlight
-        let backlight_byte = if self.wants_backlight() { 0b0000_1000 } else { 0b0000_0000 };
-        T::send(peripherals, self.display_address(), [backlight_byte])
+        let backlight_byte = if self.backlight_lit() { 0b0000_1000 } else { 0b0000_0000 };
+        T::send_retry(peripherals, self.display_address(), &[backlight_byte], SEND_ATTEMPTS)
     }
 
     /// Perform basic display setup.
@@ -118,27 +251,77 @@ pub(crate) trait I2cDisplay<T: SercomI2cController> {
         Self::short_delay();
 
         // set display to 4-bit mode
+        // (from here on, the busy flag can be read instead of waiting)
         self.transmit_nibble(peripherals, 0b0010, false)?;
         Self::short_delay();
-        self.transmit_byte(peripherals, 0b0010_1000, false)?;
-        Self::short_delay();
+
+        // 4-bit mode; displays with four rows are wired as two logical lines
+        let (_columns, rows) = self.geometry();
+        let line_flag = if rows > 1 { 0b0000_1000 } else { 0b0000_0000 };
+        let font_flag = if rows == 1 && self.font() == Font::Dots5x10 { 0b0000_0100 } else { 0b0000_0000 };
+        self.transmit_byte(peripherals, 0b0010_0000 | line_flag | font_flag, false)?;
+        self.wait_busy(peripherals)?;
 
         // disable display
         self.transmit_byte(peripherals, 0b0000_1000, false)?;
-        Self::short_delay();
+        self.wait_busy(peripherals)?;
 
         // clear display and go home
-        self.transmit_byte(peripherals, 0b0000_0001, false)?;
-        Self::long_delay();
+        self.clear(peripherals)?;
 
         // increment but don't shift
         self.transmit_byte(peripherals, 0b0000_0110, false)?;
-        Self::short_delay();
+        self.wait_busy(peripherals)?;
 
-        // enable display
-        self.transmit_byte(peripherals, 0b0000_1100, false)?;
-        Self::short_delay();
+        // enable display, without cursor
+        self.set_display_control(peripherals, true, false, false)
+    }
+
+    /// Switches the display, the underline cursor and the blinking block cursor on or off.
+    ///
+    /// Switching the display off keeps its contents, which reappear once it is switched on again.
+    fn set_display_control(&self, peripherals: &mut Peripherals, on: bool, cursor: bool, blink: bool) -> Result<(), I2cError> {
+        let on_flag = if on { 0b0000_0100 } else { 0b0000_0000 };
+        let cursor_flag = if cursor { 0b0000_0010 } else { 0b0000_0000 };
+        let blink_flag = if blink { 0b0000_0001 } else { 0b0000_0000 };
+        self.transmit_byte(peripherals, 0b0000_1000 | on_flag | cursor_flag | blink_flag, false)?;
+        self.wait_busy(peripherals)
+    }
+
+    /// Performs basic display setup, repeating it up to `attempts` times in total if the display
+    /// does not respond or stays busy (see [`I2cErrorKind::is_transient`]).
+    ///
+    /// Right after power-up, the display may not be ready yet; the whole setup is repeated after
+    /// [`SETUP_RETRY_DELAY`]. Returns the last error if all attempts fail. If `attempts` is 0, a
+    /// single attempt is made nonetheless.
+    fn basic_setup_retry(&self, peripherals: &mut Peripherals, attempts: u8) -> Result<(), I2cError> {
+        let mut attempt = 1;
+        loop {
+            match self.basic_setup(peripherals) {
+                Err(e) if e.kind.is_transient() && attempt < attempts => {
+                    delay(SETUP_RETRY_DELAY);
+                    attempt += 1;
+                },
+                other => return other,
+            }
+        }
+    }
+
+    /// Clears the display and moves the cursor to the top left.
+    ///
+    /// This is one of the slowest instructions of the display controller.
+    fn clear(&self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
+        self.transmit_byte(peripherals, 0b0000_0001, false)?;
+        Self::long_delay();
+        Ok(())
+    }
 
+    /// Moves the cursor to the top left without changing the contents of the display.
+    ///
+    /// This is one of the slowest instructions of the display controller.
+    fn home(&self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
+        self.transmit_byte(peripherals, 0b0000_0010, false)?;
+        Self::long_delay();
         Ok(())
     }
 
@@ -147,35 +330,346 @@ pub(crate) trait I2cDisplay<T: SercomI2cController> {
         self.transmit_byte(peripherals, 0b1000_0000 | location, false)
     }
 
+    /// Calculates the display location of the given column and row.
+    ///
+    /// The second row always starts at location 0x40. On displays with four rows, the third and
+    /// fourth rows continue the first and second row, respectively; e.g. on a 20x4 display, the rows
+    /// start at 0x00, 0x40, 0x14 and 0x54.
+    fn location(&self, column: u8, row: u8) -> u8 {
+        let (columns, _rows) = self.geometry();
+        let row_start = match row {
+            0 => 0x00,
+            1 => 0x40,
+            2 => columns,
+            _ => 0x40 + columns,
+        };
+        row_start + column
+    }
+
+    /// Move to the given column and row on the display.
+    ///
+    /// The column comes first, unlike the `(row, column)` order used by many other HD44780 drivers.
+    fn set_cursor(&self, peripherals: &mut Peripherals, column: u8, row: u8) -> Result<(), I2cError> {
+        self.set_location(peripherals, self.location(column, row))
+    }
+
+    /// Defines the dot pattern of the given custom character in the character generator RAM.
+    ///
+    /// Each byte of `pattern` is one row of dots, from top to bottom; the lowest five bits are the
+    /// dots from right to left. The character can then be output by writing its
+    /// [`code`](CustomChar::code) using [`write_text`](I2cDisplay::write_text). Afterwards, the
+    /// cursor is at the top left of the display.
+    fn define_custom_char(&self, peripherals: &mut Peripherals, character: CustomChar, pattern: &[u8; 8]) -> Result<(), I2cError> {
+        // set CGRAM address
+        self.transmit_byte(peripherals, 0b0100_0000 | (character.code() << 3), false)?;
+        self.wait_busy(peripherals)?;
+
+        self.write_text(peripherals, pattern.iter().map(|row| row & 0b1_1111))?;
+
+        // data would otherwise keep going into the CGRAM; switch back to the display data RAM
+        self.set_cursor(peripherals, 0, 0)
+    }
+
     /// Write text at the current location on the display.
     fn write_text<I: IntoIterator<Item = u8>>(&self, peripherals: &mut Peripherals, text: I) -> Result<(), I2cError> {
         for b in text {
+            self.transmit_byte(peripherals, b, true)?;
+            self.wait_busy(peripherals)?;
+        }
+        Ok(())
+    }
+
+    /// Write text starting at the top left of the display, continuing on the next row whenever a
+    /// row is full.
+    ///
+    /// A newline (`b'\n'`) also continues on the next row. Text that does not fit on the last row
+    /// is dropped.
+    fn write_wrapped<I: IntoIterator<Item = u8>>(&self, peripherals: &mut Peripherals, text: I) -> Result<(), I2cError> {
+        let (columns, rows) = self.geometry();
+        let mut column = 0;
+        let mut row = 0;
+        self.set_cursor(peripherals, 0, 0)?;
+        for b in text {
+            if b == b'\n' || column == columns {
+                // the display memory of a row is not followed by the next row; move there explicitly
+                row += 1;
+                if row == rows {
+                    break;
+                }
+                column = 0;
+                self.set_cursor(peripherals, 0, row)?;
+                if b == b'\n' {
+                    continue;
+                }
+            }
             self.transmit_byte(peripherals, b, true)?;
             Self::short_delay();
+            column += 1;
         }
         Ok(())
     }
+
+    /// Write text over the whole given row, cutting it off at the end of the row or padding it with
+    /// spaces up to there.
+    fn write_line<I: IntoIterator<Item = u8>>(&self, peripherals: &mut Peripherals, row: u8, text: I) -> Result<(), I2cError> {
+        let (columns, _rows) = self.geometry();
+        self.set_cursor(peripherals, 0, row)?;
+        self.write_text(
+            peripherals,
+            text.into_iter()
+                .chain(core::iter::repeat(b' '))
+                .take(usize::from(columns)),
+        )
+    }
+
+    /// Write a string at the current location on the display.
+    ///
+    /// Characters that the display cannot show are replaced by [`fallback_char`]; a few common
+    /// non-ASCII characters (such as `°`, `ä`, `ö` and `ü`) are translated into their equivalents
+    /// in the display's character ROM.
+    ///
+    /// [`fallback_char`]: I2cDisplay::fallback_char
+    fn write_str(&self, peripherals: &mut Peripherals, s: &str) -> Result<(), I2cError> {
+        let fallback = self.fallback_char();
+        self.write_text(peripherals, s.chars().map(|c| to_rom_a00(c, fallback)))
+    }
+
+    /// Obtains a writer that allows formatted text to be written at the current location on the
+    /// display using [`write!`].
+    fn writer<'p>(&self, peripherals: &'p mut Peripherals) -> I2cDisplayWriter<'_, 'p, Self, T> where Self: Sized {
+        I2cDisplayWriter {
+            display: self,
+            peripherals,
+            error: None,
+            controller: PhantomData,
+        }
+    }
 }
 
 
-/// I2C LCD on Two-Wire Interface 0.
-pub struct I2cDisplaySercom0 {
+/// Writes formatted text to an I2C display.
+///
+/// The text is transmitted character by character while it is being formatted; no buffer is
+/// required. Characters are translated as by [`I2cDisplay::write_str`].
+///
+/// As [`fmt::Write`] cannot return an [`I2cError`], the first error that occurs is stored in the
+/// writer and can be obtained using [`take_error`](I2cDisplayWriter::take_error). Once an error has
+/// occurred, all further output is rejected until the error is taken.
+pub(crate) struct I2cDisplayWriter<'d, 'p, D: I2cDisplay<T>, T: SercomI2cController> {
+    display: &'d D,
+    peripherals: &'p mut Peripherals,
+    error: Option<I2cError>,
+    controller: PhantomData<T>,
+}
+impl<'d, 'p, D: I2cDisplay<T>, T: SercomI2cController> I2cDisplayWriter<'d, 'p, D, T> {
+    /// Returns the error that occurred during writing, if any, and resets it.
+    pub fn take_error(&mut self) -> Option<I2cError> {
+        self.error.take()
+    }
+}
+impl<'d, 'p, D: I2cDisplay<T>, T: SercomI2cController> fmt::Write for I2cDisplayWriter<'d, 'p, D, T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+
+        if let Err(e) = self.display.write_str(self.peripherals, s) {
+            self.error = Some(e);
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+impl<'d, 'p, D: I2cDisplay<T>, T: SercomI2cController> DisplaySink for I2cDisplayWriter<'d, 'p, D, T> {
+    type Error = I2cError;
+
+    fn set_cursor(&mut self, column: u8, row: u8) -> Result<(), I2cError> {
+        self.display.set_cursor(self.peripherals, column, row)
+    }
+
+    fn put_str(&mut self, s: &str) -> Result<(), I2cError> {
+        self.display.write_str(self.peripherals, s)
+    }
+}
+
+
+/// I2C LCD attached to the SERCOM driven by the controller `C`.
+pub(crate) struct GenericI2cDisplay<C: SercomI2cController> {
     display_address: u8,
     wants_backlight: bool,
+    backlight_level: u8,
+    backlight_phase: u8,
+    backlight_pulse: bool,
+    geometry: (u8, u8),
+    font: Font,
+    controller: PhantomData<C>,
 }
-impl I2cDisplaySercom0 {
+impl<C: SercomI2cController> GenericI2cDisplay<C> {
+    /// Creates a display at the given 7-bit address with the given number of columns and rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `display_address` is not a valid 7-bit address or if the geometry is not supported
+    /// by the HD44780 (see [`try_new`](Self::try_new)). When called in a `const` context, this is a
+    /// compile-time error.
     pub const fn new(
         display_address: u8,
         wants_backlight: bool,
+        geometry: (u8, u8),
     ) -> Self {
-        Self {
+        match Self::try_new(display_address, wants_backlight, geometry) {
+            Some(display) => display,
+            None => panic!("invalid I2C display address or geometry"),
+        }
+    }
+
+    /// Creates a display at the given 7-bit address with the given number of columns and rows.
+    ///
+    /// Returns `None` if `display_address` is not a valid 7-bit address or if the geometry is not
+    /// supported by the HD44780 (one to four rows, at most 80 characters in total, and at most 40
+    /// characters per line on displays with up to two rows or 20 on displays with more).
+    pub const fn try_new(
+        display_address: u8,
+        wants_backlight: bool,
+        geometry: (u8, u8),
+    ) -> Option<Self> {
+        if display_address & 0b1000_0000 != 0 {
+            return None;
+        }
+
+        let (columns, rows) = geometry;
+        let max_columns = if rows > 2 { 20 } else { 40 };
+        if columns == 0 || columns > max_columns || rows == 0 || rows > 4 {
+            return None;
+        }
+
+        Some(Self {
             display_address,
             wants_backlight,
+            backlight_level: u8::MAX,
+            backlight_phase: 0,
+            backlight_pulse: true,
+            geometry,
+            font: Font::Dots5x8,
+            controller: PhantomData,
+        })
+    }
+
+    /// Returns this display with the given font.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Font::Dots5x10`] is requested for a display with more than one row. When called
+    /// in a `const` context, this is a compile-time error.
+    pub const fn with_font(mut self, font: Font) -> Self {
+        if matches!(font, Font::Dots5x10) && self.geometry.1 > 1 {
+            panic!("the 5x10 font is only available on single-line displays");
+        }
+        self.font = font;
+        self
+    }
+
+    /// The brightness of the backlight while it is wanted, from 0 (off) to 255 (fully on).
+    pub fn backlight_level(&self) -> u8 {
+        self.backlight_level
+    }
+
+    /// Sets the brightness of the backlight while it is wanted, from 0 (off) to 255 (fully on).
+    ///
+    /// The PCF8574 can only switch the backlight on or off; levels in between are produced by
+    /// [`tick_backlight`](Self::tick_backlight). At 0 and 255, the backlight is simply kept off or
+    /// on as with [`set_wants_backlight`](I2cDisplay::set_wants_backlight), without any I2C
+    /// traffic. The new level takes effect with the next call to
+    /// [`update_backlight`](I2cDisplay::update_backlight) or `tick_backlight`.
+    pub fn set_backlight_level(&mut self, level: u8) {
+        self.backlight_level = level;
+        self.backlight_pulse = level != 0;
+    }
+
+    /// Whether the backlight level is between off and fully on, so that
+    /// [`tick_backlight`](Self::tick_backlight) has to be called regularly.
+    pub fn is_dimmed(&self) -> bool {
+        self.backlight_level != 0 && self.backlight_level != u8::MAX
+    }
+
+    /// Advances the modulation of a dimmed backlight by one step, switching the backlight on or
+    /// off if required.
+    ///
+    /// Over 256 calls, the backlight is lit during `level` of them, spread out as evenly as
+    /// possible. The modulation frequency therefore depends on how often this is called: at 1024
+    /// calls per second, the backlight is switched at least 100 times per second for levels from 25
+    /// to 230, while lower and higher levels may flicker. Nothing is sent if the level is 0 or 255,
+    /// if the backlight is not wanted, or if the backlight stays as it is.
+    pub fn tick_backlight(&mut self, peripherals: &mut Peripherals) -> Result<(), I2cError> {
+        if !self.is_dimmed() || !self.wants_backlight {
+            return Ok(());
+        }
+
+        // first-order delta-sigma modulation: light up whenever the accumulator overflows
+        let (phase, pulse) = self.backlight_phase.overflowing_add(self.backlight_level);
+        self.backlight_phase = phase;
+        if pulse == self.backlight_pulse {
+            return Ok(());
         }
+        self.backlight_pulse = pulse;
+        self.update_backlight(peripherals)
     }
 }
-impl I2cDisplay<Sercom0I2cController> for I2cDisplaySercom0 {
+impl<C: SercomI2cController> I2cDisplay<C> for GenericI2cDisplay<C> {
     #[inline] fn display_address(&self) -> u8 { self.display_address }
     #[inline] fn wants_backlight(&self) -> bool { self.wants_backlight }
     #[inline] fn set_wants_backlight(&mut self, wants_backlight: bool) { self.wants_backlight = wants_backlight; }
+    #[inline] fn backlight_lit(&self) -> bool { self.wants_backlight && self.backlight_pulse }
+    #[inline] fn geometry(&self) -> (u8, u8) { self.geometry }
+    #[inline] fn font(&self) -> Font { self.font }
+}
+
+/// I2C LCD on Two-Wire Interface 0.
+pub(crate) type I2cDisplaySercom0 = GenericI2cDisplay<Sercom0I2cController>;
+
+
+/// A message that scrolls across one line of a display if it is too long to fit.
+pub(crate) struct Marquee {
+    message: &'static [u8],
+    row: u8,
+    offset: usize,
+}
+impl Marquee {
+    /// The number of blank characters between the end of the message and its next repetition.
+    const GAP: usize = 3;
+
+    /// Creates a marquee showing `message` across the whole width of the given row.
+    pub const fn new(message: &'static [u8], row: u8) -> Self {
+        Self {
+            message,
+            row,
+            offset: 0,
+        }
+    }
+
+    /// Writes the current window of the message, then advances the window by one character.
+    ///
+    /// A message that fits into the window is shown without scrolling.
+    pub fn tick_scroll<D: I2cDisplay<T>, T: SercomI2cController>(
+        &mut self,
+        display: &D,
+        peripherals: &mut Peripherals,
+    ) -> Result<(), I2cError> {
+        let (columns, _rows) = display.geometry();
+        let width = usize::from(columns);
+        let message = self.message;
+        if message.len() <= width {
+            return display.write_line(peripherals, self.row, message.iter().copied());
+        }
+
+        display.set_cursor(peripherals, 0, self.row)?;
+        let cycle_length = message.len() + Self::GAP;
+        let offset = self.offset;
+        let window = (0..width)
+            .map(|i| message.get((offset + i) % cycle_length).copied().unwrap_or(b' '));
+        display.write_text(peripherals, window)?;
+
+        self.offset = (offset + 1) % cycle_length;
+        Ok(())
+    }
 }
@@ -1,16 +1,205 @@
 //! Initialization code.
 
 
-use atsaml21g18b::Peripherals;
+use atsaml21g18b::{Interrupt, Peripherals};
+use cortex_m::peripheral::NVIC;
+
+#[cfg(not(feature = "dfll48m"))]
+use crate::board_pin;
+use crate::calibration::CalibrationData;
+use crate::dcf77::FREQUENCY_HZ;
+use crate::sync_vcell::SyncVolatileCell;
 
 
 /// The speed of the core clock, timed by XOSC.
+///
+/// If a board variant uses an oscillator with a different frequency, this is the only value that
+/// needs to be changed; the I<sup>2</sup>C and UART baud rates, the SysTick reload value, the
+/// carrier period and the phase modulation chip period are all derived from it. The carrier is
+/// generated by dividing the core clock, so the frequency must be divisible by the DCF77 carrier
+/// frequency; this is verified at compile time.
+#[cfg(not(feature = "dfll48m"))]
 pub const CORE_CLOCK_SPEED_HZ: u32 = 31_000_000;
 
+#[cfg(not(feature = "dfll48m"))]
+const _: () = assert!(
+    CORE_CLOCK_SPEED_HZ % FREQUENCY_HZ == 0,
+    "CORE_CLOCK_SPEED_HZ must be divisible by the DCF77 carrier frequency",
+);
+
+/// The factor by which the DFLL48M multiplies the frequency of the slow clock.
+///
+/// 1464 × 32.768 kHz = 47.972352 MHz, which is within the DFLL48M's operating range and divides
+/// into a carrier frequency of 77 499.76 Hz (about 3.1 ppm too slow). No multiplier in the
+/// operating range divides into 77.5 kHz exactly, but this deviation is far below what DCF77
+/// receivers can detect.
+#[cfg(feature = "dfll48m")]
+const DFLL_MULTIPLIER: u32 = 1464;
+
+/// The speed of the core clock, timed by DFLL48M, which is locked to XOSC32K.
+///
+/// See [`DFLL_MULTIPLIER`] for the achievable accuracy of the carrier frequency.
+#[cfg(feature = "dfll48m")]
+pub const CORE_CLOCK_SPEED_HZ: u32 = DFLL_MULTIPLIER * SLOW_CLOCK_SPEED_HZ;
+
+#[cfg(feature = "dfll48m")]
+const _: () = assert!(
+    (CARRIER_PERIOD as u64 * FREQUENCY_HZ as u64).abs_diff(CORE_CLOCK_SPEED_HZ as u64) * 1_000_000
+        <= 10 * CORE_CLOCK_SPEED_HZ as u64,
+    "the carrier frequency derived from CORE_CLOCK_SPEED_HZ deviates by more than 10 ppm",
+);
+
+
+/// The period of the carrier signal in core clock cycles.
+///
+/// Rounded to the nearest integer if the core clock is not divisible by the carrier frequency.
+pub const CARRIER_PERIOD: u32 = (CORE_CLOCK_SPEED_HZ + FREQUENCY_HZ / 2) / FREQUENCY_HZ;
+
 
 /// The speed of the slow clock, timed by XOSC32K.
 pub const SLOW_CLOCK_SPEED_HZ: u32 = 32_768;
 
+/// The threshold of the brown-out detector for the 3.3V supply (`BOD33.LEVEL`).
+///
+/// Level 39 corresponds to about 2.84 V, which leaves some headroom for supply ripple below 3.3 V
+/// while still detecting a failing supply long before the NVM stops working reliably.
+pub const BROWN_OUT_LEVEL: u8 = 39;
+
+/// What the brown-out detector does once the supply voltage drops below [`BROWN_OUT_LEVEL`].
+pub const BROWN_OUT_ACTION: BrownOutAction = BrownOutAction::Interrupt;
+
+
+/// The reaction of the brown-out detector to a low supply voltage.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BrownOutAction {
+    /// Reset the microcontroller.
+    Reset,
+
+    /// Raise the `SYSTEM` interrupt, after which no more NVM writes are started until the next
+    /// reset.
+    Interrupt,
+}
+impl BrownOutAction {
+    /// Converts this action to the representation in the configuration register.
+    #[inline]
+    pub const fn to_bits(&self) -> u8 {
+        match self {
+            Self::Reset => 0x1,
+            Self::Interrupt => 0x2,
+        }
+    }
+}
+
+
+/// Whether the brown-out detector has signalled a low supply voltage since the last reset.
+static BROWN_OUT_DETECTED: SyncVolatileCell<bool> = SyncVolatileCell::new(false);
+
+/// The calibration values, read from NVM once the flash wait states have been configured.
+static CALIBRATION: SyncVolatileCell<Option<CalibrationData>> = SyncVolatileCell::new(None);
+
+
+/// How many times to check whether XOSC is ready before assuming it is missing or broken.
+///
+/// At the 4 MHz the CPU runs at after reset, this takes a few hundred milliseconds, which is far
+/// longer than any oscillator should need to start up.
+#[cfg(not(feature = "dfll48m"))]
+const XOSC_STARTUP_POLLS: u32 = 200_000;
+
+/// The number of CPU cycles that correspond to 100 ms at the 4 MHz the CPU runs at after reset.
+#[cfg(not(feature = "dfll48m"))]
+const RESET_CLOCK_CYCLES_100MS: u32 = 400_000;
+
+
+/// Sets up XOSC32K and everything that is clocked by it.
+fn setup_slow_clocks(peripherals: &mut Peripherals) {
+    // initialize XOSC32K
+    peripherals.OSC32KCTRL.xosc32k.modify(|_, w| w
+        .ondemand().clear_bit() // run even if not explicitly requested
+        .runstdby().set_bit() // run in standby mode too
+        .xtalen().clear_bit() // it's a fully-fledged oscillator, not a crystal
+        .en32k().set_bit() // enable 32kHz output
+    );
+
+    // start XOSC32K
+    peripherals.OSC32KCTRL.xosc32k.modify(|_, w| w
+        .enable().set_bit()
+    );
+    while peripherals.OSC32KCTRL.status.read().xosc32krdy().bit_is_clear() {
+    }
+
+    // plug XOSC32K into GCG3
+    peripherals.GCLK.genctrl[3].modify(|_, w| w
+        .divsel().clear_bit() // interpret divisor as DIV, not 2**(DIV+1)
+        .div().variant(1) // divide by 1 (= no division)
+        .runstdby().set_bit() // run even in standby
+        .idc().clear_bit() // no need to improve duty cycle; we are not dividing
+        .oe().clear_bit() // no explicit I/O output
+        .src().xosc32k() // take time from XOSC32K
+    );
+    while peripherals.GCLK.syncbusy.read().genctrl3().bit_is_set() {
+    }
+
+    // turn on GCG3
+    peripherals.GCLK.genctrl[3].modify(|_, w| w
+        .genen().set_bit()
+    );
+    while peripherals.GCLK.syncbusy.read().genctrl3().bit_is_set() {
+    }
+
+    // plug XOSC32K into RTC
+    peripherals.OSC32KCTRL.rtcctrl.modify(|_, w| w
+        .rtcsel().xosc32k()
+    );
+
+    // connect GCG3 as slow clock to SERCOM0 through SERCOM4
+    const GCLK_SERCOM0_TO_SERCOM4_SLOW: usize = 17;
+    peripherals.GCLK.pchctrl[GCLK_SERCOM0_TO_SERCOM4_SLOW].modify(|_, w| w
+        .gen().gclk3() // take from GCG3
+        .chen().set_bit() // enable
+    );
+}
+
+
+/// Connects GCG0 to the peripherals that are clocked by the core clock.
+fn connect_core_clock(peripherals: &mut Peripherals) {
+    // GCG0 is always connected to the CPU core (SAM L21 datasheet § 17.1, Note)
+
+    // connect GCG0 as core clock to SERCOM0
+    const GCLK_SERCOM0_CORE: usize = 18;
+    peripherals.GCLK.pchctrl[GCLK_SERCOM0_CORE].modify(|_, w| w
+        .gen().gclk0() // take from GCG0
+        .chen().set_bit() // enable
+    );
+
+    // connect GCG0 as core clock to SERCOM3
+    const GCLK_SERCOM3_CORE: usize = 21;
+    peripherals.GCLK.pchctrl[GCLK_SERCOM3_CORE].modify(|_, w| w
+        .gen().gclk0() // take from GCG0
+        .chen().set_bit() // enable
+    );
+
+    // connect GCG0 to TCC0
+    const GCLK_TCC0: usize = 25;
+    peripherals.GCLK.pchctrl[GCLK_TCC0].modify(|_, w| w
+        .gen().gclk0() // take from GCG0
+        .chen().set_bit() // enable
+    );
+
+    // connect GCG0 to TC0
+    const GCLK_TC0: usize = 27;
+    peripherals.GCLK.pchctrl[GCLK_TC0].modify(|_, w| w
+        .gen().gclk0() // take from GCG0
+        .chen().set_bit() // enable
+    );
+
+    // connect GCG0 to ADC
+    const GCLK_ADC: usize = 30;
+    peripherals.GCLK.pchctrl[GCLK_ADC].modify(|_, w| w
+        .gen().gclk0() // take from GCG0
+        .chen().set_bit() // enable
+    );
+}
+
 
 /// Sets up the microcontroller's clocks that will be used.
 ///
@@ -28,10 +217,25 @@ pub const SLOW_CLOCK_SPEED_HZ: u32 = 32_768;
 /// │ 32.768 kHz ├─────┬──┤ 32.768 kHz ├────────┤ slow: 32.768 kHz │
 /// └────────────┘     │  └────────────┘     │  └──────────────────┘
 ///                    │                     │
+///                    │                     │  ┌────────────────┐
+///                    │                     ├──┤ SERCOM3 (UART) │
+///                    │                     │  │ core: 31 MHz   │
+///                    │                     │  └────────────────┘
+///                    │                     │
 ///                    │                     │  ┌────────────┐
 ///                    │                     │  │ TCC0 (PWM) │
-///                    │                     └──┤ 31 MHz     │
-///                    │                        └────────────┘
+///                    │                     ├──┤ 31 MHz     │
+///                    │                     │  └────────────┘
+///                    │                     │
+///                    │                     │  ┌─────────────────┐
+///                    │                     │  │ TC0 (PRN chips) │
+///                    │                     ├──┤ 31 MHz          │
+///                    │                     │  └─────────────────┘
+///                    │                     │
+///                    │                     │  ┌───────────────────┐
+///                    │                     │  │ ADC (temperature) │
+///                    │                     └──┤ 31 MHz            │
+///                    │                        └───────────────────┘
 ///                    │
 ///                    │                        ┌─────────────────────┐
 ///                    │                        │ RTC (state updates) │
@@ -43,6 +247,12 @@ pub const SLOW_CLOCK_SPEED_HZ: u32 = 32_768;
 ///
 /// 31 MHz has been chosen as the frequency for `XOSC` because it is readily divisible by 77.5 kHz,
 /// the modulation frequency of DCF77.
+///
+/// If XOSC does not start up, the LED signals this and the microcontroller halts (see
+/// [`xosc_failed`]).
+///
+/// If the `dfll48m` feature is enabled, [`setup_clocks_dfll`] is used instead.
+#[cfg(not(feature = "dfll48m"))]
 pub(crate) fn setup_clocks(peripherals: &mut Peripherals) {
     // initialize XOSC
     peripherals.OSCCTRL.xoscctrl.modify(|_, w| w
@@ -55,7 +265,12 @@ pub(crate) fn setup_clocks(peripherals: &mut Peripherals) {
     peripherals.OSCCTRL.xoscctrl.modify(|_, w| w
         .enable().set_bit()
     );
+    let mut polls_left = XOSC_STARTUP_POLLS;
     while peripherals.OSCCTRL.status.read().xoscrdy().bit_is_clear() {
+        if polls_left == 0 {
+            xosc_failed(peripherals);
+        }
+        polls_left -= 1;
     }
 
     // changes to GCLK registers must be synchronized
@@ -81,63 +296,207 @@ pub(crate) fn setup_clocks(peripherals: &mut Peripherals) {
     while peripherals.GCLK.syncbusy.read().genctrl0().bit_is_set() {
     }
 
-    // GCG0 is always connected to the CPU core (SAM L21 datasheet § 17.1, Note)
+    connect_core_clock(peripherals);
+    setup_slow_clocks(peripherals);
+}
 
-    // connect GCG0 as core clock to SERCOM0
-    const GCLK_SERCOM0_CORE: usize = 18;
-    peripherals.GCLK.pchctrl[GCLK_SERCOM0_CORE].modify(|_, w| w
-        .gen().gclk0() // take from GCG0
+
+/// Signals that XOSC has failed to start and halts.
+///
+/// Without XOSC, the carrier cannot be generated at the correct frequency, so there is no point in
+/// continuing. Instead, the LED on PA27 blinks three times in quick succession, followed by a
+/// pause, which is easily told apart from the steady blinking of a panic.
+#[cfg(not(feature = "dfll48m"))]
+fn xosc_failed(peripherals: &mut Peripherals) -> ! {
+    board_pin!(set_io, peripherals, PA, 27);
+    board_pin!(make_output, peripherals, PA, 27);
+
+    loop {
+        for _ in 0..3 {
+            board_pin!(set_high, peripherals, PA, 27);
+            cortex_m::asm::delay(RESET_CLOCK_CYCLES_100MS);
+            board_pin!(set_low, peripherals, PA, 27);
+            cortex_m::asm::delay(2 * RESET_CLOCK_CYCLES_100MS);
+        }
+        cortex_m::asm::delay(10 * RESET_CLOCK_CYCLES_100MS);
+    }
+}
+
+
+/// Sets up the microcontroller's clocks that will be used, deriving the core clock from DFLL48M.
+///
+/// This is an alternative to [`setup_clocks`] for boards without a 31 MHz oscillator; it is used
+/// if the `dfll48m` feature is enabled. The clock setup is the same, except that the core clock is
+/// generated by DFLL48M in closed-loop mode with XOSC32K (via GCG3) as its reference:
+///
+/// ```
+/// ┌────────────┐        ┌────────────┐        ┌───────────────┐        ┌───────────────┐
+/// │ XOSC32K    │        │ GCG3       │        │ DFLL48M       │        │ GCG0          │
+/// │ 32.768 kHz ├────────┤ 32.768 kHz ├────────┤ 47.972352 MHz ├────────┤ 47.972352 MHz ├─── ...
+/// └────────────┘        └────────────┘        └───────────────┘        └───────────────┘
+/// ```
+///
+/// The achievable accuracy of the carrier frequency is documented at [`DFLL_MULTIPLIER`].
+#[cfg(feature = "dfll48m")]
+pub(crate) fn setup_clocks_dfll(peripherals: &mut Peripherals) {
+    // the DFLL needs its reference clock
+    setup_slow_clocks(peripherals);
+
+    // connect GCG3 as reference clock to DFLL48M
+    const GCLK_DFLL48M_REFERENCE: usize = 0;
+    peripherals.GCLK.pchctrl[GCLK_DFLL48M_REFERENCE].modify(|_, w| w
+        .gen().gclk3() // take from GCG3
         .chen().set_bit() // enable
     );
 
-    // connect GCG0 to TCC0
-    const GCLK_TCC0: usize = 25;
-    peripherals.GCLK.pchctrl[GCLK_TCC0].modify(|_, w| w
-        .gen().gclk0() // take from GCG0
-        .chen().set_bit() // enable
+    // enable DFLL48M in open-loop mode first
+    // (the other DFLL registers may only be written while it is enabled)
+    peripherals.OSCCTRL.dfllctrl.write(|w| w
+        .enable().set_bit()
     );
+    while peripherals.OSCCTRL.status.read().dfllrdy().bit_is_clear() {
+    }
 
-    // initialize XOSC32K
-    peripherals.OSC32KCTRL.xosc32k.modify(|_, w| w
+    // set the multiplier and the maximum steps when looking for a lock
+    peripherals.OSCCTRL.dfllmul.write(|w| unsafe { w
+        .mul().bits(DFLL_MULTIPLIER as u16)
+        .cstep().bits(7) // about a ninth of the coarse range
+        .fstep().bits(63) // a sixteenth of the fine range
+    });
+    while peripherals.OSCCTRL.status.read().dfllrdy().bit_is_clear() {
+    }
+
+    // start from the factory calibration value
+    peripherals.OSCCTRL.dfllval.write(|w| unsafe { w
+        .coarse().bits(calibration().dfll48m_coarse)
+        .fine().bits(512) // middle of the fine range
+    });
+    while peripherals.OSCCTRL.status.read().dfllrdy().bit_is_clear() {
+    }
+
+    // switch to closed-loop mode
+    peripherals.OSCCTRL.dfllctrl.modify(|_, w| w
+        .mode().set_bit() // closed-loop mode
+        .waitlock().set_bit() // don't output the clock before it is locked
         .ondemand().clear_bit() // run even if not explicitly requested
         .runstdby().set_bit() // run in standby mode too
-        .xtalen().clear_bit() // it's a fully-fledged oscillator, not a crystal
-        .en32k().set_bit() // enable 32kHz output
-    );
-
-    // start XOSC32K
-    peripherals.OSC32KCTRL.xosc32k.modify(|_, w| w
-        .enable().set_bit()
     );
+    while peripherals.OSCCTRL.status.read().dfllrdy().bit_is_clear() {
+    }
+    loop {
+        let status = peripherals.OSCCTRL.status.read();
+        let locked =
+            status.dflllckc().bit_is_set()
+            && status.dflllckf().bit_is_set()
+        ;
+        if locked {
+            break;
+        }
+    }
 
-    // plug XOSC32K into GCG3
-    peripherals.GCLK.genctrl[3].modify(|_, w| w
+    // plug DFLL48M into GCG0
+    peripherals.GCLK.genctrl[0].modify(|_, w| w
         .divsel().clear_bit() // interpret divisor as DIV, not 2**(DIV+1)
         .div().variant(1) // divide by 1 (= no division)
         .runstdby().set_bit() // run even in standby
         .idc().clear_bit() // no need to improve duty cycle; we are not dividing
         .oe().clear_bit() // no explicit I/O output
-        .src().xosc32k() // take time from XOSC32K
+        .src().dfll48m() // take time from DFLL48M
     );
-    while peripherals.GCLK.syncbusy.read().genctrl3().bit_is_set() {
+    while peripherals.GCLK.syncbusy.read().genctrl0().bit_is_set() {
     }
 
-    // plug XOSC32K into RTC
-    peripherals.OSC32KCTRL.rtcctrl.modify(|_, w| w
-        .rtcsel().xosc32k()
+    // turn on GCG0
+    peripherals.GCLK.genctrl[0].modify(|_, w| w
+        .genen().set_bit()
     );
+    while peripherals.GCLK.syncbusy.read().genctrl0().bit_is_set() {
+    }
 
-    // connect GCG3 as slow clock to SERCOM0
-    const GCLK_SERCOM0_TO_SERCOM4_SLOW: usize = 18;
-    peripherals.GCLK.pchctrl[GCLK_SERCOM0_TO_SERCOM4_SLOW].modify(|_, w| w
-        .gen().gclk3() // take from GCG3
-        .chen().set_bit() // enable
+    connect_core_clock(peripherals);
+}
+
+
+/// Sets up the brown-out detector for the 3.3V supply according to [`BROWN_OUT_LEVEL`] and
+/// [`BROWN_OUT_ACTION`].
+fn setup_brown_out_detector(peripherals: &mut Peripherals) {
+    // the configuration can only be changed while the detector is disabled
+    peripherals.SUPC.bod33.modify(|_, w| w
+        .enable().clear_bit()
+    );
+    while peripherals.SUPC.status.read().b33srdy().bit_is_clear() {
+    }
+
+    peripherals.SUPC.bod33.modify(|_, w| unsafe { w
+        .level().bits(BROWN_OUT_LEVEL)
+        .action().bits(BROWN_OUT_ACTION.to_bits())
+        .hyst().set_bit() // don't trigger repeatedly while hovering around the threshold
+        .actcfg().clear_bit() // sample continuously in active mode
+        .runstdby().set_bit() // keep watching in standby mode too
+    });
+    while peripherals.SUPC.status.read().b33srdy().bit_is_clear() {
+    }
+
+    if BROWN_OUT_ACTION == BrownOutAction::Interrupt {
+        peripherals.SUPC.intenset.write(|w| w
+            .bod33det().set_bit()
+        );
+        unsafe {
+            NVIC::unmask(Interrupt::SYSTEM)
+        }
+    }
+
+    // start
+    peripherals.SUPC.bod33.modify(|_, w| w
+        .enable().set_bit()
     );
+    while peripherals.SUPC.status.read().bod33rdy().bit_is_clear() {
+    }
+}
+
+
+/// Records that the brown-out detector has signalled a low supply voltage. Called by the `SYSTEM`
+/// interrupt.
+pub(crate) fn handle_brown_out(peripherals: &mut Peripherals) {
+    if peripherals.SUPC.intflag.read().bod33det().bit_is_clear() {
+        return;
+    }
+    unsafe {
+        peripherals.SUPC.intflag.write_with_zero(|w| w
+            .bod33det().set_bit()
+        )
+    };
+    BROWN_OUT_DETECTED.set(true);
+}
+
+
+/// Returns whether the brown-out detector has signalled a low supply voltage since the last reset.
+///
+/// NVM writes should not be started if this is the case.
+pub(crate) fn brown_out_detected() -> bool {
+    BROWN_OUT_DETECTED.get()
+}
+
+
+/// Returns the calibration values read from NVM during initialization.
+///
+/// Use this instead of reading the calibration area directly; it is only read by
+/// [`initialize_microcontroller`] once the flash wait states are appropriate for the core clock.
+///
+/// # Panics
+///
+/// Panics if called before [`initialize_microcontroller`].
+pub(crate) fn calibration() -> CalibrationData {
+    CALIBRATION.get()
+        .expect("calibration values requested before initialization")
 }
 
 
 /// Performs microcontroller initialization.
 pub(crate) fn initialize_microcontroller(peripherals: &mut Peripherals) {
+    // protect the NVM from being written with a sagging supply
+    setup_brown_out_detector(peripherals);
+
     // we want to switch to performance level 2 (PL2) as soon as possible;
     // there isn't much documentation on flash wait states in the datasheet,
     // but a wait state count of 2 has been listed in the datasheet for 3.3V and PL2
@@ -146,6 +505,9 @@ pub(crate) fn initialize_microcontroller(peripherals: &mut Peripherals) {
         .rws().dual()
     );
 
+    // read the calibration values only now that the flash wait states are set up
+    CALIBRATION.set(Some(crate::calibration::read_calibration()));
+
     // switch to PL2
     peripherals.PM.plcfg.modify(|_, w| w
         .plsel().pl2()
@@ -153,5 +515,8 @@ pub(crate) fn initialize_microcontroller(peripherals: &mut Peripherals) {
     while peripherals.PM.intflag.read().plrdy().bit_is_clear() {
     }
 
+    #[cfg(not(feature = "dfll48m"))]
     setup_clocks(peripherals);
+    #[cfg(feature = "dfll48m")]
+    setup_clocks_dfll(peripherals);
 }
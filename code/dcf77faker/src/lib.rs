@@ -0,0 +1,18 @@
+//! The hardware-independent parts of the DCF77 faker.
+//!
+//! These modules are split off from the firmware so that they can be compiled and tested on the
+//! host, e.g. using:
+//!
+//! ```plain
+//! cargo test --lib --target x86_64-unknown-linux-gnu
+//! ```
+//!
+//! (The default target is the microcontroller, which cannot run the tests.)
+
+
+#![cfg_attr(not(test), no_std)]
+
+
+pub mod calibration;
+pub mod dcf77;
+pub mod transmitter;
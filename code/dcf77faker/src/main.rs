@@ -2,35 +2,135 @@
 #![no_main]
 
 
-mod calibration;
-mod dcf77;
+mod adc;
+mod backlight;
+mod big_digits;
+mod config;
 mod i2c_controller;
 mod i2c_display;
 mod init;
+mod nvm;
+mod persist;
+mod phase_modulation;
 mod pin;
+mod power;
 mod pwm;
 mod rtc;
 mod sync_vcell;
 mod tick;
+mod uart;
+mod wdt;
 
 
+use core::fmt::Write;
 use core::panic::PanicInfo;
 
 use atsaml21g18b::{CorePeripherals, interrupt, Peripherals};
 use cortex_m_rt::entry;
+use dcf77faker::{calibration, dcf77, transmitter};
 
+use crate::backlight::BacklightSchedule;
+use crate::big_digits::BigDigits;
+use crate::config::Config;
 use crate::dcf77::Dcf77Data;
-use crate::i2c_controller::{Sercom0I2cController, SercomI2cController};
-use crate::i2c_display::{I2cDisplay, I2cDisplaySercom0};
-use crate::init::CORE_CLOCK_SPEED_HZ;
+use crate::i2c_controller::{
+    FAST_MODE_MAX_SPEED_HZ, I2cError, I2cErrorKind, Sercom0I2cController, SercomI2cController,
+};
+use crate::i2c_display::{I2cDisplay, I2cDisplaySercom0, Marquee};
+use crate::init::CARRIER_PERIOD;
+use crate::persist::TimeStore;
 use crate::pin::PeripheralIndex;
 use crate::pwm::{Tcc0Pwm, TccPwm};
 use crate::sync_vcell::SyncVolatileCell;
+use crate::transmitter::{
+    Amplitude, CarrierSink, SecondReset, show_hour_minute_separator, show_status, show_time,
+    Transmitter,
+};
+use crate::uart::{Sercom3Uart, SercomUart, UartWriter};
+use crate::wdt::WatchdogPeriod;
+
+
+/// The watchdog period (about 2 seconds), or `None` to disable the watchdog.
+///
+/// The watchdog is fed once per second by the RTC interrupt, but only if the main loop has
+/// processed the previous time update.
+const WATCHDOG_PERIOD: Option<WatchdogPeriod> = Some(WatchdogPeriod::Cycles2048);
+
+/// The duty cycle of the carrier at full amplitude.
+///
+/// The amplitude of the fundamental (77.5 kHz) component of a square wave with duty cycle `d` is
+/// proportional to `sin(π·d)`, which is maximal at 50%.
+const CARRIER_DUTY_FULL: u32 = CARRIER_PERIOD / 2;
+
+/// The duty cycle of the carrier at reduced amplitude.
+///
+/// DCF77 reduces the amplitude of its carrier to about 15% at the start of each second, for 100 ms
+/// to transmit a 0 bit or for 200 ms to transmit a 1 bit, and transmits at full amplitude for the
+/// rest of the second. The bit is thereby encoded in the length of the reduction, while its depth
+/// is always the same. A duty cycle of 4.8% yields `sin(π·0.048)` ≈ 15% of the full amplitude.
+const CARRIER_DUTY_REDUCED: u32 = CARRIER_PERIOD * 48 / 1000;
+
+const _: () = assert!(
+    0 < CARRIER_DUTY_REDUCED && CARRIER_DUTY_REDUCED < CARRIER_DUTY_FULL,
+    "reduced carrier duty cycle must be between 0 and the full duty cycle",
+);
+
+/// What the reset-seconds button does.
+///
+/// By default, the current minute starts over at second 0, which is what is needed to align the
+/// seconds with a reference clock. [`SecondReset::RoundToMinute`] instead jumps to the closest
+/// minute boundary, which also corrects the minute when the button is pressed a little too late.
+const SECOND_RESET: SecondReset = SecondReset::Zero;
+
+/// For how many minute markers the carrier stays off in the transmitter-down test mode.
+const TRANSMITTER_DOWN_MINUTES: u16 = 5;
+
+/// The number of columns and rows of the display.
+const DISPLAY_GEOMETRY: (u8, u8) = (20, 4);
+
+/// The display according to the default configuration, whose address and geometry are checked at
+/// compile time.
+const I2C_DISPLAY: I2cDisplaySercom0 = I2cDisplaySercom0::new(
+    Config::DEFAULT.display_address,
+    Config::DEFAULT.backlight,
+    DISPLAY_GEOMETRY,
+);
+
+/// How many times to attempt setting up the display, which might not be ready yet after power-up.
+const DISPLAY_SETUP_ATTEMPTS: u8 = 5;
+
+
+/// The state of the transmission, advanced by the RTC interrupt.
+static TRANSMITTER: SyncVolatileCell<Transmitter> = SyncVolatileCell::new(Transmitter::new(Dcf77Data::new(), 59));
+static UPDATE_TIME: SyncVolatileCell<bool> = SyncVolatileCell::new(false);
 
+/// The number of RTC ticks since the RTC has been started.
+///
+/// Unlike the tick clock, this keeps counting while the CPU is in standby.
+static RTC_TICKS: SyncVolatileCell<u32> = SyncVolatileCell::new(0);
 
-static SECOND: SyncVolatileCell<u8> = SyncVolatileCell::new(59);
-static DCF77_DATA: SyncVolatileCell<Dcf77Data> = SyncVolatileCell::new(Dcf77Data::new());
-static UPDATE_TIME: SyncVolatileCell<bool> = SyncVolatileCell::new(false);
+/// The value of the RTC counter at which the next tick is due.
+static NEXT_TICK_COUNT: SyncVolatileCell<u32> = SyncVolatileCell::new(crate::rtc::COUNTS_PER_TICK);
+
+/// The number of minute markers until the carrier is switched back on, or 0 if it is on.
+static MINUTES_UNTIL_TRANSMITTER_UP: SyncVolatileCell<u16> = SyncVolatileCell::new(0);
+
+/// Whether the reset-seconds button was pressed during the previous RTC tick.
+static RESET_BUTTON_WAS_PRESSED: SyncVolatileCell<bool> = SyncVolatileCell::new(false);
+
+/// The kind of the most recent I<sup>2</sup>C error that occurred while talking to the display.
+///
+/// Reset to `None` as soon as the display has been updated successfully again.
+static I2C_ERROR: SyncVolatileCell<Option<I2cErrorKind>> = SyncVolatileCell::new(None);
+
+
+/// Returns the current RTC tick within the second (0 through 31).
+///
+/// This allows the main loop to time animations more finely than the once-per-second time update.
+fn subsecond() -> u8 {
+    // the RTC interrupt rewrites the whole transmitter; don't read half of an update
+    cortex_m::interrupt::free(|_| TRANSMITTER.get()).tick_in_second()
+}
 
 
 #[inline]
@@ -41,6 +141,172 @@ fn noppage() {
 }
 
 
+/// Switches off the carrier until the `minutes`-th minute marker from now, simulating a transmitter
+/// outage.
+///
+/// The time keeps running in the meantime, so the first frame after the outage is correct. The
+/// carrier comes back at the start of a minute marker, so that the frame is received in full.
+/// `minutes` must be at least 1.
+fn transmitter_down(peripherals: &mut Peripherals, minutes: u16) {
+    assert!(minutes > 0);
+    cortex_m::interrupt::free(|_| {
+        Tcc0Pwm::blank(peripherals);
+        Tcc0Pwm::stop_generation(peripherals);
+        board_pin!(set_high, peripherals, PA, 19);
+        MINUTES_UNTIL_TRANSMITTER_UP.set(minutes);
+    });
+}
+
+
+/// Records the outcome of a display operation so that the status LED can indicate errors.
+fn record_i2c_result(result: Result<(), I2cError>) {
+    match result {
+        Ok(()) => I2C_ERROR.set(None),
+        Err(e) => I2C_ERROR.set(Some(e.kind)),
+    }
+}
+
+
+/// Writes the addresses of all devices responding on the I<sup>2</sup>C bus to the debug UART.
+fn log_i2c_devices(peripherals: &mut Peripherals) {
+    // collect the addresses first; the UART writer and the scan cannot both borrow the peripherals
+    let mut found: u128 = 0;
+    let result = Sercom0I2cController::scan(peripherals, |address| found |= 1 << address);
+
+    let mut uart = UartWriter::<Sercom3Uart>::new(peripherals);
+    let _ = write!(uart, "I2C devices:");
+    for address in 0..128 {
+        if found & (1 << address) != 0 {
+            let _ = write!(uart, " 0x{:02X}", address);
+        }
+    }
+    let _ = match result {
+        Ok(()) => writeln!(uart),
+        Err(e) => writeln!(uart, " (scan aborted: {})", e),
+    };
+}
+
+
+/// Whether the status LED should be lit during the given RTC tick (0 through 31) of a second.
+///
+/// If an I<sup>2</sup>C error is pending, the LED blinks a number of times at the start of each
+/// second, depending on the kind of error:
+///
+/// | blinks | error                                    |
+/// | ------ | ---------------------------------------- |
+/// | 1      | [`ArbitrationLost`](I2cErrorKind::ArbitrationLost) |
+/// | 2      | [`BusError`](I2cErrorKind::BusError)               |
+/// | 3      | [`NotAcknowledged`](I2cErrorKind::NotAcknowledged) |
+/// | 4      | [`InvalidAddress`](I2cErrorKind::InvalidAddress)   |
+/// | 5      | [`Timeout`](I2cErrorKind::Timeout)                 |
+/// | 6      | [`DeviceBusy`](I2cErrorKind::DeviceBusy)           |
+///
+/// Each blink is two ticks on, two ticks off. Without a pending error, the LED stays dark.
+const fn status_led_lit(error: Option<I2cErrorKind>, tick: u8) -> bool {
+    let blinks = match error {
+        None => return false,
+        Some(I2cErrorKind::ArbitrationLost) => 1,
+        Some(I2cErrorKind::BusError) => 2,
+        Some(I2cErrorKind::NotAcknowledged) => 3,
+        Some(I2cErrorKind::InvalidAddress) => 4,
+        Some(I2cErrorKind::Timeout) => 5,
+        Some(I2cErrorKind::DeviceBusy) => 6,
+    };
+    tick < blinks * 4 && tick % 4 < 2
+}
+
+
+/// The number of RTC ticks (1/32 s each) for which the pulse-per-second output stays high at the
+/// start of a regular second.
+///
+/// One tick (31.25 ms) is the shortest pulse the RTC interrupt can produce.
+const PPS_TICKS: u8 = 1;
+
+/// The number of RTC ticks for which the pulse-per-second output stays high at the start of a
+/// minute (second :00), which allows an oscilloscope to trigger on the start of the frame.
+const PPS_MINUTE_TICKS: u8 = 16;
+
+
+/// Whether the pulse-per-second output should be high during the given RTC tick (0 through 31) of
+/// the given second.
+const fn pps_high(second: u8, tick: u8) -> bool {
+    let pulse_ticks = if second == 0 { PPS_MINUTE_TICKS } else { PPS_TICKS };
+    tick < pulse_ticks
+}
+
+
+/// The message scrolling across the top line of the display.
+const MARQUEE_MESSAGE: &[u8] = b"DCF77 Faker - simulated DCF77 time signal";
+
+/// The number of RTC ticks (1/32 s each) between two steps of the scrolling message.
+const MARQUEE_INTERVAL_TICKS: u32 = 10;
+
+/// The display row across which the message scrolls.
+const MARQUEE_ROW: u8 = 0;
+
+/// The display row on which the transmission status is shown.
+const STATUS_ROW: u8 = 1;
+
+/// The display row on which the date and time are shown.
+const TIME_ROW: u8 = 2;
+
+/// Whether the hour and the minute are shown as big digits across the time row and the one below it
+/// instead of showing the date and time on the time row alone.
+///
+/// The big digits take up 19 columns and the date is not shown.
+const BIG_TIME: bool = false;
+
+/// Whether the colon between the hour and the minute blinks, being hidden during the second half
+/// of each second.
+const BLINK_COLON: bool = true;
+
+/// The RTC tick within the second at which the blinking colon is hidden.
+const COLON_HIDE_TICK: u8 = 16;
+
+
+/// Outputs the carrier using TCC0, with phase modulation by TC0.
+///
+/// PA19 mirrors the envelope for debugging (high = reduced amplitude or no carrier).
+struct Tcc0Carrier<'p> {
+    peripherals: &'p mut Peripherals,
+}
+impl<'p> CarrierSink for Tcc0Carrier<'p> {
+    fn set_amplitude(&mut self, amplitude: Amplitude) {
+        match amplitude {
+            Amplitude::Full => {
+                Tcc0Pwm::set_duty_cycle(self.peripherals, CARRIER_DUTY_FULL);
+                Tcc0Pwm::unblank(self.peripherals);
+                board_pin!(set_low, self.peripherals, PA, 19);
+            },
+            Amplitude::Reduced => {
+                Tcc0Pwm::set_duty_cycle(self.peripherals, CARRIER_DUTY_REDUCED);
+                Tcc0Pwm::unblank(self.peripherals);
+                board_pin!(set_high, self.peripherals, PA, 19);
+            },
+            Amplitude::Off => {
+                Tcc0Pwm::blank(self.peripherals);
+                board_pin!(set_high, self.peripherals, PA, 19);
+            },
+        }
+    }
+
+    fn start_second(&mut self, bit: Option<bool>) {
+        crate::phase_modulation::start_second(self.peripherals, bit.unwrap_or(false));
+    }
+}
+
+
+/// Swallows the carrier while the transmitter is down.
+struct NoCarrier;
+impl CarrierSink for NoCarrier {
+    fn set_amplitude(&mut self, _amplitude: Amplitude) {
+    }
+
+    fn start_second(&mut self, _bit: Option<bool>) {
+    }
+}
+
+
 #[panic_handler]
 fn panicked(_reason: &PanicInfo) -> ! {
     let peripherals = unsafe {
@@ -53,9 +319,7 @@ fn panicked(_reason: &PanicInfo) -> ! {
     board_pin!(make_output, peripherals, PA, 27);
 
     loop {
-        board_pin!(set_high, peripherals, PA, 27);
-        noppage();
-        board_pin!(set_low, peripherals, PA, 27);
+        board_pin!(toggle, peripherals, PA, 27);
         noppage();
     }
 }
@@ -72,113 +336,361 @@ fn main() -> ! {
     crate::tick::enable_tick_clock(&mut core_peripherals);
 
     // set pins as I/O:
-    // PA16 = input with pull-up (reset-seconds button)
-    // PA17 = input with pull-up (increment-minute button)
+    // PA16 = input with pull-up (reset-seconds button; held during startup: configuration console)
+    // PA17 = input with pull-up (increment-minute button; pressed alone: toggle backlight)
     // PA18 = input with pull-up (increment-hour button)
+    // PA19 = output (carrier envelope for debugging; high = reduced amplitude)
+    // PA20 = output (pulse per second; rising edge at the start of each second)
     // PA27 = output (LED)
-    board_pin!(set_io, peripherals, PA, 16, 17, 18, 27);
+    board_pin!(set_io, peripherals, PA, 16, 17, 18, 19, 20, 27);
     board_pin!(make_input, peripherals, PA, 16, 17, 18);
     board_pin!(enable_pull, peripherals, PA, 16, 17, 18);
     board_pin!(set_high, peripherals, PA, 16, 17, 18);
-    board_pin!(make_output, peripherals, PA, 27);
+    board_pin!(set_low, peripherals, PA, 19, 20);
+    board_pin!(make_output, peripherals, PA, 19, 20, 27);
 
     // hand over pins to peripherals:
     // PA04 = TCC0/WO[0] (E)
     // PA08 = SERCOM0/PAD[0] (C)
     // PA09 = SERCOM0/PAD[1] (C)
-    board_pin!(set_peripheral, peripherals, PA, 4, 8, 9);
+    // PA22 = SERCOM3/PAD[0] (C) (debug UART TX)
+    // PA23 = SERCOM3/PAD[1] (C) (debug UART RX)
+    board_pin!(set_peripheral, peripherals, PA, 4, 8, 9, 22, 23);
     board_pin!(select_peripheral, peripherals, PeripheralIndex::E, PA, 4);
-    board_pin!(select_peripheral, peripherals, PeripheralIndex::C, PA, 8, 9);
+    board_pin!(select_peripheral, peripherals, PeripheralIndex::C, PA, 8, 9, 22, 23);
+
+    // set up debug UART
+    Sercom3Uart::setup_uart(&mut peripherals);
+
+    // load the configuration; holding the reset-seconds button (active low) during startup opens the
+    // configuration console on the debug UART
+    let mut config = Config::load();
+    if !board_pin!(read_pin, peripherals, PA, 16) {
+        crate::config::run_console::<Sercom3Uart>(&mut peripherals, &mut config);
+    }
 
-    // set up I2C
-    Sercom0I2cController::setup_controller(&mut peripherals);
+    // set up I2C (recovering with setup_controller later keeps the speed)
+    if config.fast_i2c {
+        Sercom0I2cController::setup_controller_with_speed(&mut peripherals, FAST_MODE_MAX_SPEED_HZ);
+    } else {
+        Sercom0I2cController::setup_controller(&mut peripherals);
+    }
+    let _ = writeln!(
+        UartWriter::<Sercom3Uart>::new(&mut peripherals),
+        "I2C speed: {} Hz",
+        Sercom0I2cController::actual_speed_hz(),
+    );
 
     // set up display
-    let i2c_display = I2cDisplaySercom0::new(0b010_0111, true);
-    let _ = i2c_display.basic_setup(&mut peripherals);
-    let _ = i2c_display.set_location(&mut peripherals, 0);
-    let _ = i2c_display.write_text(&mut peripherals, *b"DCF77 Faker");
+    let mut i2c_display = I2cDisplaySercom0::try_new(config.display_address, config.backlight, DISPLAY_GEOMETRY)
+        .unwrap_or(I2C_DISPLAY);
+    let mut marquee = Marquee::new(MARQUEE_MESSAGE, MARQUEE_ROW);
+    let display_result = i2c_display.basic_setup_retry(&mut peripherals, DISPLAY_SETUP_ATTEMPTS)
+        .and_then(|_| if BIG_TIME { i2c_display.define_big_digit_glyphs(&mut peripherals) } else { Ok(()) })
+        .and_then(|_| marquee.tick_scroll(&i2c_display, &mut peripherals));
+    if display_result.is_err() {
+        // the display might be at a different address
+        log_i2c_devices(&mut peripherals);
+    }
+    record_i2c_result(display_result);
 
     // set up PWM
     Tcc0Pwm::setup_pwm(&mut peripherals);
     Tcc0Pwm::set_period_and_duty_cycle(
         &mut peripherals,
-        CORE_CLOCK_SPEED_HZ / dcf77::FREQUENCY_HZ,
+        CARRIER_PERIOD,
         0,
     );
     Tcc0Pwm::start_generation(&mut peripherals);
 
+    // set up temperature measurement
+    crate::adc::setup_adc(&mut peripherals);
+
+    // set up phase modulation
+    crate::phase_modulation::setup_chip_timer(&mut peripherals);
+
+    // restore the most recently stored time
+    let (mut time_store, stored_time) = TimeStore::scan();
+    let mut transmitter = match stored_time {
+        Some((data, second)) => Transmitter::new(data, second),
+        None => TRANSMITTER.get(),
+    };
+    transmitter.data_mut().follow_summer_time = config.follow_summer_time;
+    TRANSMITTER.set(transmitter);
+
+    // switch the backlight according to the schedule
+    let mut backlight_schedule = BacklightSchedule::new(
+        config.backlight,
+        config.night_start_hour,
+        config.night_end_hour,
+    );
+    i2c_display.set_backlight_level(config.backlight_level);
+    i2c_display.set_wants_backlight(backlight_schedule.backlight_at(transmitter.data().hour()));
+    record_i2c_result(i2c_display.update_backlight(&mut peripherals));
+
+    // set up watchdog
+    crate::wdt::setup_watchdog(&mut peripherals, WATCHDOG_PERIOD);
+
+    // set up RTC (ticks the seconds and the status LED)
+    crate::rtc::setup_rtc(&mut peripherals, config.rtc_correction);
+    crate::rtc::set_periodic_interrupt(&mut peripherals, i2c_display.is_dimmed());
+    crate::rtc::enable_interrupt();
+
+    // sleep while waiting for the RTC (the carrier keeps running)
+    crate::power::setup_standby(&mut peripherals, &mut core_peripherals.SCB);
+
+    let mut next_marquee_tick = RTC_TICKS.get().wrapping_add(MARQUEE_INTERVAL_TICKS);
+    let mut test_combo_was_pressed = false;
+    let mut down_combo_was_pressed = false;
+    let mut minute_button_was_pressed = false;
+    let mut minute_button_in_combo = false;
+    let mut colon_hidden = false;
     loop {
         while !UPDATE_TIME.get() {
+            // scroll the message while waiting (the RTC interrupt must not be held up by I2C)
+            if RTC_TICKS.get().wrapping_sub(next_marquee_tick) < u32::MAX / 2 {
+                record_i2c_result(marquee.tick_scroll(&i2c_display, &mut peripherals));
+                next_marquee_tick = next_marquee_tick.wrapping_add(MARQUEE_INTERVAL_TICKS);
+            }
+
+            // hide the colon halfway through the second; the next time update shows it again
+            if BLINK_COLON && !colon_hidden && subsecond() >= COLON_HIDE_TICK {
+                if BIG_TIME {
+                    record_i2c_result(i2c_display.show_big_hour_minute_separator(&mut peripherals, TIME_ROW, false));
+                } else {
+                    let mut display_writer = i2c_display.writer(&mut peripherals);
+                    record_i2c_result(show_hour_minute_separator(&mut display_writer, TIME_ROW, false));
+                }
+                colon_hidden = true;
+            }
+
+            // a dimmed backlight is switched on and off whenever the CPU wakes up, which the periodic
+            // RTC interrupt makes happen often enough for it not to flicker
+            record_i2c_result(i2c_display.tick_backlight(&mut peripherals));
+
+            // an interrupt that becomes pending while interrupts are disabled still wakes the CPU, so
+            // an update arriving between the check and the sleep is not missed
+            cortex_m::interrupt::free(|_| {
+                if !UPDATE_TIME.get() {
+                    crate::power::sleep();
+                }
+            });
         }
 
         UPDATE_TIME.set(false);
-        let second = SECOND.get();
+
+        // test mode: pressing the increment-minute and increment-hour buttons (active low) together
+        // toggles the abnormal-operation bit, simulating a transmitter fault from the next frame on
+        let test_combo_pressed =
+            !board_pin!(read_pin, peripherals, PA, 17)
+            && !board_pin!(read_pin, peripherals, PA, 18)
+        ;
+        if test_combo_pressed && !test_combo_was_pressed {
+            cortex_m::interrupt::free(|_| {
+                let mut transmitter = TRANSMITTER.get();
+                let data = transmitter.data_mut();
+                data.set_abnormal_operation(!data.abnormal_operation);
+                TRANSMITTER.set(transmitter);
+            });
+        }
+        test_combo_was_pressed = test_combo_pressed;
+
+        // test mode: holding the increment-hour button and then pressing the reset-seconds button
+        // switches the carrier off for a few minutes; doing so again while it is off brings it back
+        // at the next minute marker
+        let down_combo_pressed =
+            !board_pin!(read_pin, peripherals, PA, 16)
+            && !board_pin!(read_pin, peripherals, PA, 18)
+        ;
+        if down_combo_pressed && !down_combo_was_pressed {
+            if MINUTES_UNTIL_TRANSMITTER_UP.get() == 0 {
+                transmitter_down(&mut peripherals, TRANSMITTER_DOWN_MINUTES);
+            } else {
+                cortex_m::interrupt::free(|_| MINUTES_UNTIL_TRANSMITTER_UP.set(1));
+            }
+        }
+        down_combo_was_pressed = down_combo_pressed;
+
+        // pressing and releasing the increment-minute button on its own toggles the backlight; this
+        // overrides the schedule until the night starts or ends
+        let minute_button_pressed = !board_pin!(read_pin, peripherals, PA, 17);
+        if test_combo_pressed {
+            minute_button_in_combo = true;
+        }
+        if !minute_button_pressed && minute_button_was_pressed && !minute_button_in_combo {
+            let backlight = backlight_schedule.toggle(i2c_display.wants_backlight());
+            i2c_display.set_wants_backlight(backlight);
+            record_i2c_result(i2c_display.update_backlight(&mut peripherals));
+        }
+        if !minute_button_pressed {
+            minute_button_in_combo = false;
+        }
+        minute_button_was_pressed = minute_button_pressed;
 
         // send over the new time
-        let mut time_info: [u8; 17] = *b"xx.xx.xx xx:xx:xx";
-        let data = DCF77_DATA.get();
-        time_info[0] = b'0' + data.day_of_month_tens;
-        time_info[1] = b'0' + data.day_of_month_ones;
-        time_info[3] = if data.month_ten { b'1' } else { b'0' };
-        time_info[4] = b'0' + data.month_ones;
-        time_info[6] = b'0' + data.year_in_century_tens;
-        time_info[7] = b'0' + data.year_in_century_ones;
-        time_info[9] = b'0' + data.hour_tens;
-        time_info[10] = b'0' + data.hour_ones;
-        time_info[12] = b'0' + data.minute_tens;
-        time_info[13] = b'0' + data.minute_ones;
-        time_info[15] = b'0' + (second / 10);
-        time_info[16] = b'0' + (second % 10);
+        // the RTC interrupt rewrites the whole transmitter; take a consistent copy
+        let transmitter = cortex_m::interrupt::free(|_| TRANSMITTER.get());
+        let data = transmitter.data();
+        let second = transmitter.second();
+        let time_result = if BIG_TIME {
+            i2c_display.draw_big_time(&mut peripherals, TIME_ROW, data.hour(), data.minute(), second)
+        } else {
+            show_time(&mut i2c_display.writer(&mut peripherals), TIME_ROW, &data, second)
+        };
+        let mut display_writer = i2c_display.writer(&mut peripherals);
+        record_i2c_result(
+            time_result
+                .and_then(|_| show_status(&mut display_writer, STATUS_ROW, &data, transmitter.transmitted_bit()))
+        );
+        colon_hidden = false;
+
+        // store the time and follow the backlight schedule once a minute
+        if second == 0 {
+            time_store.store(&mut peripherals, &data, second);
+
+            let backlight = backlight_schedule.backlight_at(data.hour());
+            if backlight != i2c_display.wants_backlight() {
+                i2c_display.set_wants_backlight(backlight);
+                record_i2c_result(i2c_display.update_backlight(&mut peripherals));
+            }
+        }
+
+        if I2C_ERROR.get() == Some(I2cErrorKind::Timeout) {
+            // the controller is stuck in the middle of a transfer; start over
+            Sercom0I2cController::setup_controller(&mut peripherals);
+        }
 
-        let _ = i2c_display.set_location(&mut peripherals, 20);
-        let _ = i2c_display.write_text(&mut peripherals, time_info);
+        // log the state to the debug UART if enabled in the configuration
+        if config.debug_log {
+            let temperature = crate::adc::read_temperature_millicelsius(&mut peripherals);
+            let mut uart = UartWriter::<Sercom3Uart>::new(&mut peripherals);
+            let _ = writeln!(uart, "{:?}", data);
+            let _ = writeln!(uart, "frame: 0b{:059b}", data.frame_bits());
+            let _ = writeln!(uart, "awake for: {} ms", crate::tick::uptime_ms());
+            let temperature_sign = if temperature < 0 { "-" } else { "" };
+            let _ = writeln!(
+                uart,
+                "temperature: {}{}.{:03} \u{B0}C",
+                temperature_sign, temperature.abs() / 1000, temperature.abs() % 1000,
+            );
+            if let Some(error_kind) = I2C_ERROR.get() {
+                let _ = writeln!(uart, "I2C error: {}", error_kind);
+            }
+            let minutes_until_up = MINUTES_UNTIL_TRANSMITTER_UP.get();
+            if minutes_until_up > 0 {
+                let _ = writeln!(uart, "transmitter down for {} more minute marker(s)", minutes_until_up);
+            }
+        }
     }
 }
 
 
 #[interrupt]
-fn RTC() {
-    // fired 32x per second
-    static mut COUNTER: u8 = 31;
-    static mut MINUTE: u64 = 0;
+fn TC0() {
+    // fired once per phase modulation chip
+    let mut peripherals = unsafe { Peripherals::steal() };
+    crate::phase_modulation::next_chip(&mut peripherals);
+}
+
 
+#[interrupt]
+fn SYSTEM() {
+    // fired by the brown-out detector
     let mut peripherals = unsafe { Peripherals::steal() };
+    crate::init::handle_brown_out(&mut peripherals);
+}
+
 
-    // increment counter
-    *COUNTER = (*COUNTER + 1) % 32;
-    if *COUNTER != 0 {
+#[interrupt]
+fn RTC() {
+    // fired 32x per second by the compare match and, while the backlight is dimmed, by the periodic
+    // interrupt, which only wakes up the main loop to modulate the backlight
+    let mut peripherals = unsafe { Peripherals::steal() };
+    crate::rtc::acknowledge_periodic(&mut peripherals);
+    if !crate::rtc::tick_pending(&mut peripherals) {
         return;
     }
+    crate::rtc::acknowledge_tick(&mut peripherals);
+
+    // the counter keeps running while interrupts are disabled, the CPU wakes up from standby or
+    // the NVM is being written; handle every tick that is due according to the counter so that the
+    // seconds never slip against it, even if the interrupt is late
+    let mut next_tick_count = NEXT_TICK_COUNT.get();
+    loop {
+        while crate::rtc::count(&mut peripherals).wrapping_sub(next_tick_count) < u32::MAX / 2 {
+            rtc_tick(&mut peripherals);
+            next_tick_count = next_tick_count.wrapping_add(crate::rtc::COUNTS_PER_TICK);
+        }
+        crate::rtc::set_next_tick(&mut peripherals, next_tick_count);
 
-    // increment second
-    let mut second = SECOND.get() + 1;
-    if second == 60 {
-        second = 0;
+        // if the counter has reached the compare value before it was set, the match is missed;
+        // handle that tick right away instead
+        if crate::rtc::count(&mut peripherals).wrapping_sub(next_tick_count) >= u32::MAX / 2 {
+            break;
+        }
     }
-    SECOND.set(second);
-    if second == 59 {
-        // turn off modulation
-        Tcc0Pwm::set_duty_cycle(&mut peripherals, 0);
+    NEXT_TICK_COUNT.set(next_tick_count);
+}
 
-        // calculate a new minute
-        let mut dcf77_data = DCF77_DATA.get();
-        dcf77_data.increment_minute();
-        DCF77_DATA.set(dcf77_data);
-        *MINUTE = dcf77_data.to_bits();
-    } else {
-        // regular behavior
 
-        // lop the last bit off of the minute
-        let long_duty_cycle = (*MINUTE & 0b1) != 0;
-        *MINUTE >>= 1;
+/// Handles a single RTC tick.
+fn rtc_tick(peripherals: &mut Peripherals) {
+    RTC_TICKS.set(RTC_TICKS.get().wrapping_add(1));
 
-        let period = init::CORE_CLOCK_SPEED_HZ / dcf77::FREQUENCY_HZ;
-        if long_duty_cycle {
-            Tcc0Pwm::set_duty_cycle(&mut peripherals, period / 2);
-        } else {
-            Tcc0Pwm::set_duty_cycle(&mut peripherals, period / 44);
+    // advance the transmission
+    let mut transmitter = TRANSMITTER.get();
+
+    // the reset-seconds button (active low) is checked here rather than in the main loop so that
+    // the new second 0 starts within a tick of pressing it; it does not reset the seconds while the
+    // increment-hour button is held, as that combination controls the transmitter-down test mode
+    let reset_button_pressed = !board_pin!(read_pin, peripherals, PA, 16);
+    let hour_button_pressed = !board_pin!(read_pin, peripherals, PA, 18);
+    if reset_button_pressed && !RESET_BUTTON_WAS_PRESSED.get() && !hour_button_pressed {
+        transmitter.reset_second(SECOND_RESET);
+    }
+    RESET_BUTTON_WAS_PRESSED.set(reset_button_pressed);
+
+    let minutes_until_up = MINUTES_UNTIL_TRANSMITTER_UP.get();
+    let new_second = if minutes_until_up > 0 {
+        // the transmitter is down; keep the time running without a carrier
+        let new_second = transmitter.tick(&mut NoCarrier);
+        if new_second && transmitter.transmitted_bit().is_none() {
+            MINUTES_UNTIL_TRANSMITTER_UP.set(minutes_until_up - 1);
+            if minutes_until_up == 1 {
+                // back on air, starting with the minute marker (full amplitude, no reduction)
+                Tcc0Pwm::start_generation(peripherals);
+                let mut carrier = Tcc0Carrier { peripherals: &mut *peripherals };
+                carrier.set_amplitude(Amplitude::Full);
+                carrier.start_second(None);
+            }
         }
+        new_second
+    } else {
+        transmitter.tick(&mut Tcc0Carrier { peripherals: &mut *peripherals })
+    };
+    TRANSMITTER.set(transmitter);
+
+    // update the pulse-per-second output
+    if pps_high(transmitter.second(), transmitter.tick_in_second()) {
+        board_pin!(set_high, peripherals, PA, 20);
+    } else {
+        board_pin!(set_low, peripherals, PA, 20);
+    }
+
+    // update the status LED
+    if status_led_lit(I2C_ERROR.get(), transmitter.tick_in_second()) {
+        board_pin!(set_high, peripherals, PA, 27);
+    } else {
+        board_pin!(set_low, peripherals, PA, 27);
+    }
+
+    if !new_second {
+        return;
+    }
+
+    // if the main loop has processed the previous update, it is still alive => feed the watchdog
+    if !UPDATE_TIME.get() {
+        crate::wdt::feed(peripherals);
     }
 
     // update time on the display
@@ -0,0 +1,105 @@
+//! Access to the read-while-write EEPROM (RWWEE) section of the NVM.
+//!
+//! The RWWEE section can be erased and written without stalling code execution from the main flash
+//! array. It is divided into rows of four pages each; only whole rows can be erased, and erased
+//! bits read as 1.
+//!
+//! The last row is reserved for the configuration (see [`crate::config`]); the rest holds the
+//! stored time (see [`crate::persist`]).
+
+
+use atsaml21g18b::Peripherals;
+
+
+/// The start address of the RWWEE section.
+const RWWEE_START: usize = 0x0040_0000;
+
+/// The size of the RWWEE section, in bytes.
+const RWWEE_SIZE: usize = 8 * 1024;
+
+/// The size of an NVM page, in bytes.
+pub(crate) const PAGE_SIZE: usize = 64;
+
+/// The number of pages in an NVM row (the unit of erasure).
+pub(crate) const PAGES_PER_ROW: usize = 4;
+
+/// The number of pages in the RWWEE section.
+pub(crate) const PAGE_COUNT: usize = RWWEE_SIZE / PAGE_SIZE;
+
+/// The first page of the row reserved for the configuration.
+pub(crate) const CONFIG_PAGE: usize = PAGE_COUNT - PAGES_PER_ROW;
+
+/// The key that must be written to `CTRLA.CMDEX` for the command to be executed.
+const CMDEX_KEY: u8 = 0xA5;
+
+pub(crate) const CMD_PAGE_BUFFER_CLEAR: u8 = 0x44;
+pub(crate) const CMD_RWWEE_ERASE_ROW: u8 = 0x1A;
+pub(crate) const CMD_RWWEE_WRITE_PAGE: u8 = 0x1C;
+
+
+/// The address of the given RWWEE page.
+pub(crate) const fn page_address(page: usize) -> usize {
+    RWWEE_START + page * PAGE_SIZE
+}
+
+
+/// Reads the first `N` 32-bit words of the given page.
+pub(crate) fn read_words<const N: usize>(page: usize) -> [u32; N] {
+    let page_ptr = page_address(page) as *const u32;
+    let mut words = [0; N];
+    for (i, word) in words.iter_mut().enumerate() {
+        *word = unsafe { page_ptr.add(i).read_volatile() };
+    }
+    words
+}
+
+
+/// Whether the given page is erased.
+pub(crate) fn page_is_blank(page: usize) -> bool {
+    let page_ptr = page_address(page) as *const u32;
+    (0..PAGE_SIZE / 4)
+        .all(|i| unsafe { page_ptr.add(i).read_volatile() } == 0xFFFF_FFFF)
+}
+
+
+/// Executes the given NVM command on the given address, waiting until the controller is ready
+/// before and after.
+pub(crate) fn execute_command(peripherals: &mut Peripherals, command: u8, address: usize) {
+    let nvmctrl = &peripherals.NVMCTRL;
+
+    while nvmctrl.intflag.read().ready().bit_is_clear() {
+    }
+
+    // the address is given in 16-bit words
+    nvmctrl.addr.write(|w| w
+        .addr().variant((address / 2) as u32)
+    );
+    nvmctrl.ctrla.write(|w| unsafe { w
+        .cmdex().bits(CMDEX_KEY)
+        .cmd().bits(command)
+    });
+
+    while nvmctrl.intflag.read().ready().bit_is_clear() {
+    }
+}
+
+
+/// Switches the NVM controller to manual page writes and loads the given words into the page
+/// buffer for the given page.
+///
+/// The page is only written once [`CMD_RWWEE_WRITE_PAGE`] is executed on it.
+pub(crate) fn fill_page_buffer(peripherals: &mut Peripherals, page: usize, words: &[u32]) {
+    let address = page_address(page);
+
+    // we tell the controller when to write the page
+    peripherals.NVMCTRL.ctrlb.modify(|_, w| w
+        .manw().set_bit()
+    );
+
+    // fill the page buffer (only 32-bit writes are allowed)
+    execute_command(peripherals, CMD_PAGE_BUFFER_CLEAR, address);
+    let page_ptr = address as *mut u32;
+    for (i, word) in words.iter().enumerate() {
+        unsafe { page_ptr.add(i).write_volatile(*word) };
+    }
+}
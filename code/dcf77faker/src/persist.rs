@@ -0,0 +1,216 @@
+//! Persistence of the current time across resets.
+//!
+//! The time is stored in the read-while-write EEPROM (RWWEE) section of the NVM (see
+//! [`crate::nvm`]), except for its last row, which holds the configuration.
+//!
+//! Flash can only be erased a whole row (four pages) at a time and endures a limited number of
+//! erase cycles. To spread the wear, every record is written to the page following the most recent
+//! one, wrapping around at the end of the area; a row is only erased when the first of its pages
+//! is about to be written. At one record per minute, each row is erased once every 124 minutes.
+//!
+//! Each record has the following layout (in 32-bit little-endian words):
+//!
+//! | word | contents                                                                   |
+//! | ---- | -------------------------------------------------------------------------- |
+//! | 0    | magic value and layout version ([`MAGIC_AND_VERSION`])                     |
+//! | 1    | sequence number, incremented with every record                             |
+//! | 2    | civil warning bits (15:0), flags (23:16), second (31:24)                   |
+//! | 3    | minute ones, minute tens, hour ones, hour tens (one byte each, LSB first)  |
+//! | 4    | day of month ones and tens, day of week, month ones (one byte each)        |
+//! | 5    | month ten, year in century ones and tens, century (one byte each)          |
+//! | 6    | bitwise complement of the XOR of words 0 through 5                         |
+
+
+use atsaml21g18b::Peripherals;
+
+use crate::dcf77::Dcf77Data;
+use crate::nvm::{
+    CMD_RWWEE_ERASE_ROW, CMD_RWWEE_WRITE_PAGE, CONFIG_PAGE, execute_command, fill_page_buffer,
+    page_address, page_is_blank, PAGES_PER_ROW, read_words,
+};
+
+
+/// The number of pages used for records: all of the RWWEE section up to the configuration.
+const PAGE_COUNT: usize = CONFIG_PAGE;
+
+/// The number of 32-bit words in a record.
+const RECORD_WORDS: usize = 7;
+
+/// The value identifying a valid record of the current layout.
+///
+/// The lowest byte is the layout version.
+const MAGIC_AND_VERSION: u32 = 0xDCF7_7F02;
+
+const FLAG_ABNORMAL_OPERATION: u32 = 1 << 0;
+const FLAG_SUMMER_ANNOUNCEMENT: u32 = 1 << 1;
+const FLAG_CEST: u32 = 1 << 2;
+const FLAG_CET: u32 = 1 << 3;
+const FLAG_LEAP_SECOND_ANNOUNCEMENT: u32 = 1 << 4;
+
+
+const fn checksum(words: &[u32; RECORD_WORDS]) -> u32 {
+    let mut value = 0;
+    let mut i = 0;
+    while i < RECORD_WORDS - 1 {
+        value ^= words[i];
+        i += 1;
+    }
+    !value
+}
+
+
+const fn encode(sequence: u32, data: &Dcf77Data, second: u8) -> [u32; RECORD_WORDS] {
+    let mut flags = 0;
+    if data.abnormal_operation {
+        flags |= FLAG_ABNORMAL_OPERATION;
+    }
+    if data.summer_announcement {
+        flags |= FLAG_SUMMER_ANNOUNCEMENT;
+    }
+    if data.cest {
+        flags |= FLAG_CEST;
+    }
+    if data.cet {
+        flags |= FLAG_CET;
+    }
+    if data.leap_second_announcement {
+        flags |= FLAG_LEAP_SECOND_ANNOUNCEMENT;
+    }
+
+    let mut words = [
+        MAGIC_AND_VERSION,
+        sequence,
+        (data.civil_warning as u32) | (flags << 16) | ((second as u32) << 24),
+        (data.minute_ones as u32)
+            | ((data.minute_tens as u32) << 8)
+            | ((data.hour_ones as u32) << 16)
+            | ((data.hour_tens as u32) << 24),
+        (data.day_of_month_ones as u32)
+            | ((data.day_of_month_tens as u32) << 8)
+            | ((data.day_of_week as u32) << 16)
+            | ((data.month_ones as u32) << 24),
+        (data.month_ten as u32)
+            | ((data.year_in_century_ones as u32) << 8)
+            | ((data.year_in_century_tens as u32) << 16)
+            | ((data.century as u32) << 24),
+        0,
+    ];
+    words[RECORD_WORDS - 1] = checksum(&words);
+    words
+}
+
+
+const fn decode(words: &[u32; RECORD_WORDS]) -> Option<(u32, Dcf77Data, u8)> {
+    if words[0] != MAGIC_AND_VERSION || words[RECORD_WORDS - 1] != checksum(words) {
+        return None;
+    }
+
+    let flags = (words[2] >> 16) & 0xFF;
+    let data = Dcf77Data {
+        civil_warning: (words[2] & 0xFFFF) as u16,
+        abnormal_operation: flags & FLAG_ABNORMAL_OPERATION != 0,
+        summer_announcement: flags & FLAG_SUMMER_ANNOUNCEMENT != 0,
+        cest: flags & FLAG_CEST != 0,
+        cet: flags & FLAG_CET != 0,
+        leap_second_announcement: flags & FLAG_LEAP_SECOND_ANNOUNCEMENT != 0,
+        minute_ones: (words[3] & 0xFF) as u8,
+        minute_tens: ((words[3] >> 8) & 0xFF) as u8,
+        hour_ones: ((words[3] >> 16) & 0xFF) as u8,
+        hour_tens: ((words[3] >> 24) & 0xFF) as u8,
+        day_of_month_ones: (words[4] & 0xFF) as u8,
+        day_of_month_tens: ((words[4] >> 8) & 0xFF) as u8,
+        day_of_week: ((words[4] >> 16) & 0xFF) as u8,
+        month_ones: ((words[4] >> 24) & 0xFF) as u8,
+        month_ten: words[5] & 0xFF != 0,
+        year_in_century_ones: ((words[5] >> 8) & 0xFF) as u8,
+        year_in_century_tens: ((words[5] >> 16) & 0xFF) as u8,
+        century: ((words[5] >> 24) & 0xFF) as u8,
+        follow_summer_time: true,
+    };
+    if data.check().is_err() {
+        // encoding the frame of an invalid time would trip the assertion in to_bits
+        return None;
+    }
+    let second = ((words[2] >> 24) & 0xFF) as u8;
+    Some((words[1], data, second))
+}
+
+
+/// Stores the current time in the NVM and finds it again after a reset.
+pub(crate) struct TimeStore {
+    next_page: usize,
+    next_sequence: u32,
+}
+impl TimeStore {
+    /// Scans the NVM for the most recently stored time.
+    ///
+    /// Returns a store that continues after the most recent record, as well as the time and second
+    /// contained in that record. If no valid record is found (e.g. because the NVM has never been
+    /// written), the time is `None` and the caller should fall back to a default.
+    pub fn scan() -> (Self, Option<(Dcf77Data, u8)>) {
+        let mut latest: Option<(usize, u32, Dcf77Data, u8)> = None;
+        for page in 0..PAGE_COUNT {
+            if let Some((sequence, data, second)) = decode(&read_words(page)) {
+                let is_newer = match latest {
+                    Some((_, latest_sequence, _, _)) => sequence > latest_sequence,
+                    None => true,
+                };
+                if is_newer {
+                    latest = Some((page, sequence, data, second));
+                }
+            }
+        }
+
+        match latest {
+            Some((page, sequence, data, second)) => {
+                let mut next_page = (page + 1) % PAGE_COUNT;
+                if next_page % PAGES_PER_ROW != 0 && !page_is_blank(next_page) {
+                    // a write was probably interrupted; skip to the next row to have it erased
+                    next_page = ((next_page / PAGES_PER_ROW + 1) * PAGES_PER_ROW) % PAGE_COUNT;
+                }
+                let store = Self {
+                    next_page,
+                    next_sequence: sequence.wrapping_add(1),
+                };
+                (store, Some((data, second)))
+            },
+            None => {
+                let store = Self {
+                    next_page: 0,
+                    next_sequence: 0,
+                };
+                (store, None)
+            },
+        }
+    }
+
+    /// Stores the given time and second in the NVM.
+    ///
+    /// Storing erases an NVM row every few calls, which wears out the flash; this function should
+    /// therefore not be called more often than once per minute.
+    ///
+    /// Nothing is written once the brown-out detector has signalled a low supply voltage, as the
+    /// page might be corrupted by the power failing during the write.
+    pub fn store(&mut self, peripherals: &mut Peripherals, data: &Dcf77Data, second: u8) {
+        if crate::init::brown_out_detected() {
+            return;
+        }
+
+        let address = page_address(self.next_page);
+
+        if self.next_page % PAGES_PER_ROW == 0 {
+            execute_command(peripherals, CMD_RWWEE_ERASE_ROW, address);
+        }
+
+        fill_page_buffer(peripherals, self.next_page, &encode(self.next_sequence, data, second));
+
+        if crate::init::brown_out_detected() {
+            // the supply started failing while we were erasing; leave the row blank
+            return;
+        }
+        execute_command(peripherals, CMD_RWWEE_WRITE_PAGE, address);
+
+        self.next_page = (self.next_page + 1) % PAGE_COUNT;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+    }
+}
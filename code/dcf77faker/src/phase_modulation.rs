@@ -0,0 +1,211 @@
+//! Phase modulation of the DCF77 carrier with a pseudo-random noise (PRN) sequence.
+//!
+//! In addition to the amplitude modulation, DCF77 modulates the phase of its carrier with a
+//! sequence of 512 pseudo-random chips, starting 200 ms into each second. Each chip lasts 120
+//! carrier cycles (about 1.55 ms), so the sequence ends about 993 ms into the second. The sequence
+//! is transmitted unchanged if the bit of the current second is 0 and inverted if it is 1; a
+//! receiver correlating against the sequence thereby obtains the time information with much better
+//! noise immunity than from the amplitude modulation.
+//!
+//! The sequence consists of the 511 states of a 9-bit linear feedback shift register with feedback
+//! taps at stages 5 and 9, initialized to all ones, followed by a single 0 chip.
+//!
+//! As on the real transmitter, the phase deviates by ±15.6° from that of the unmodulated carrier
+//! (advanced for a 0 chip, retarded for a 1 chip) and returns to it after the sequence. Each
+//! change of phase is made by lengthening or shortening a single carrier period (see
+//! [`TccPwm::shift_phase`]).
+//!
+//! The chips are timed by TC0 and TC1, paired into a 32-bit counter, which is restarted at the
+//! beginning of each second by the RTC interrupt. Its first period lasts until the start of the
+//! sequence and the following ones one chip each; after the sequence, the counter is stopped, so
+//! that the CPU is only woken up while the sequence is being transmitted.
+
+
+use atsaml21g18b::{Interrupt, Peripherals};
+use cortex_m::peripheral::NVIC;
+
+use crate::init::{CARRIER_PERIOD, CORE_CLOCK_SPEED_HZ};
+use crate::pwm::{Tcc0Pwm, TccPwm};
+use crate::sync_vcell::SyncVolatileCell;
+
+
+/// The number of chips in the PRN sequence.
+const PRN_LENGTH: usize = 512;
+
+/// The number of carrier cycles per chip.
+const CARRIER_CYCLES_PER_CHIP: u32 = 120;
+
+/// The number of counter ticks (core clock cycles) per chip.
+const CHIP_TICKS: u32 = CARRIER_PERIOD * CARRIER_CYCLES_PER_CHIP;
+
+/// The number of counter ticks between the start of the second and the start of the sequence
+/// (200 ms).
+const TICKS_BEFORE_SEQUENCE: u32 = CORE_CLOCK_SPEED_HZ / 5;
+
+/// The number of core clock cycles by which the carrier period is changed to shift the phase by
+/// 15.6°, rounded to the nearest cycle.
+const PHASE_DEVIATION_TICKS: i32 = ((CARRIER_PERIOD * 156 + 1800) / 3600) as i32;
+
+
+const fn generate_prn_sequence() -> [u32; PRN_LENGTH / 32] {
+    let mut words = [0; PRN_LENGTH / 32];
+    let mut register: u16 = 0b1_1111_1111;
+    let mut i = 0;
+
+    // the last chip is always 0
+    while i < PRN_LENGTH - 1 {
+        // output from stage 9, feedback from stages 5 and 9
+        let output = (register >> 8) & 0b1;
+        let feedback = ((register >> 8) ^ (register >> 4)) & 0b1;
+        register = ((register << 1) | feedback) & 0b1_1111_1111;
+
+        if output != 0 {
+            words[i / 32] |= 1 << (i % 32);
+        }
+        i += 1;
+    }
+
+    words
+}
+
+
+/// The PRN sequence, one bit per chip, starting with the least significant bit of the first word.
+const PRN_SEQUENCE: [u32; PRN_LENGTH / 32] = generate_prn_sequence();
+
+
+/// The index of the next chip to transmit; [`PRN_LENGTH`] once the sequence is over.
+static CHIP_INDEX: SyncVolatileCell<u16> = SyncVolatileCell::new(PRN_LENGTH as u16);
+
+/// The bit being transmitted during the current second.
+static DATA_BIT: SyncVolatileCell<bool> = SyncVolatileCell::new(false);
+
+/// The current deviation of the carrier phase: 1 if advanced, -1 if retarded, 0 if unmodulated.
+static PHASE: SyncVolatileCell<i8> = SyncVolatileCell::new(0);
+
+
+fn get_tc_register_block() -> &'static atsaml21g18b::tc0::COUNT32 {
+    unsafe { (&*atsaml21g18b::TC0::PTR).count32() }
+}
+
+
+/// Changes the deviation of the carrier phase (see [`PHASE`]).
+fn set_phase(peripherals: &mut Peripherals, phase: i8) {
+    let steps = (phase - PHASE.get()) as i32;
+    if steps != 0 {
+        // a shorter period advances the phase
+        Tcc0Pwm::shift_phase(peripherals, -steps * PHASE_DEVIATION_TICKS);
+        PHASE.set(phase);
+    }
+}
+
+
+/// Sets up TC0 and TC1 as a 32-bit counter firing an interrupt at the start of the sequence and
+/// after each chip.
+///
+/// TC0 and TC1 must be connected to the core clock before calling this function. The counter only
+/// starts with the first call to [`start_second`].
+pub(crate) fn setup_chip_timer(peripherals: &mut Peripherals) {
+    // TC1 is the slave of TC0 in 32-bit mode and needs its bus clock as well
+    peripherals.MCLK.apbcmask.modify(|_, w| w
+        .tc0_().set_bit()
+        .tc1_().set_bit()
+    );
+
+    let register_block = get_tc_register_block();
+
+    // reset TC
+    register_block.ctrla.modify(|_, w| w
+        .swrst().set_bit()
+    );
+    while register_block.syncbusy.read().swrst().bit_is_set() {
+    }
+
+    // basic configuration
+    register_block.ctrla.modify(|_, w| w
+        .mode().count32() // 32-bit counter (TC0 paired with TC1)
+        .prescsync().presc() // reload/reset counter on tick of prescaled clock
+        .runstdby().set_bit() // run TC0 in standby
+        .prescaler().div1() // count core clock cycles
+    );
+    register_block.wave.modify(|_, w| w
+        .wavegen().mfrq() // CC0 defines the top value
+    );
+
+    // interrupt on overflow
+    register_block.intenset.write(|w| w
+        .ovf().set_bit()
+    );
+
+    // enable, but keep the counter stopped until the first second starts
+    register_block.ctrla.modify(|_, w| w
+        .enable().set_bit()
+    );
+    while register_block.syncbusy.read().enable().bit_is_set() {
+    }
+    register_block.ctrlbset.write(|w| w
+        .cmd().stop()
+    );
+    while register_block.syncbusy.read().ctrlb().bit_is_set() {
+    }
+
+    unsafe {
+        NVIC::unmask(Interrupt::TC0)
+    }
+}
+
+
+/// Restarts the PRN sequence at the beginning of a second.
+///
+/// The sequence is inverted if `data_bit` is set.
+pub(crate) fn start_second(peripherals: &mut Peripherals, data_bit: bool) {
+    let register_block = get_tc_register_block();
+    register_block.ctrlbset.write(|w| w
+        .cmd().retrigger() // restart from 0, starting the counter if it has been stopped
+    );
+    while register_block.syncbusy.read().ctrlb().bit_is_set() {
+    }
+
+    // the first period lasts until the start of the sequence; the buffered top value for the
+    // following chips is taken over when it ends
+    register_block.cc[0].write(|w| w
+        .cc().variant(TICKS_BEFORE_SEQUENCE - 1)
+    );
+    while register_block.syncbusy.read().cc0().bit_is_set() {
+    }
+    register_block.ccbuf[0].write(|w| w
+        .ccbuf().variant(CHIP_TICKS - 1)
+    );
+
+    DATA_BIT.set(data_bit);
+    CHIP_INDEX.set(0);
+
+    // in case the previous sequence has been cut short
+    set_phase(peripherals, 0);
+}
+
+
+/// Outputs the next chip. Called by the TC0 interrupt.
+pub(crate) fn next_chip(peripherals: &mut Peripherals) {
+    let register_block = get_tc_register_block();
+    unsafe {
+        register_block.intflag.write_with_zero(|w| w
+            .ovf().set_bit()
+        )
+    };
+
+    let index = CHIP_INDEX.get() as usize;
+    if index >= PRN_LENGTH {
+        // sequence is over; return to the regular phase and wait for the next second
+        set_phase(peripherals, 0);
+        register_block.ctrlbset.write(|w| w
+            .cmd().stop()
+        );
+        while register_block.syncbusy.read().ctrlb().bit_is_set() {
+        }
+        return;
+    }
+    CHIP_INDEX.set((index + 1) as u16);
+
+    let chip = (PRN_SEQUENCE[index / 32] >> (index % 32)) & 0b1 != 0;
+    set_phase(peripherals, if chip != DATA_BIT.get() { -1 } else { 1 });
+}
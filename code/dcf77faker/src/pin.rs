@@ -123,6 +123,11 @@ macro_rules! board_pin {
             .outclr().variant(board_pin!(@bitmasking, 0 $(, $pinnum)+)) // equivalent to .out().clear_bit() but no R-M-W
         )
     };
+    (toggle, $peri:expr, $pinbank:ident $(, $pinnum:expr)+) => {
+        board_pin!(pinbank_to_outtgl_reg, $peri.PORT, $pinbank).write(|w| w
+            .outtgl().variant(board_pin!(@bitmasking, 0 $(, $pinnum)+)) // inverts the output without R-M-W
+        )
+    };
 
     (pinbank_to_cfg_reg, $port:expr, PA) => ($port.pincfg0_);
     (pinbank_to_cfg_reg, $port:expr, PB) => ($port.pincfg1_);
@@ -134,6 +139,8 @@ macro_rules! board_pin {
     (pinbank_to_outset_reg, $port:expr, PB) => ($port.outset1);
     (pinbank_to_outclr_reg, $port:expr, PA) => ($port.outclr0);
     (pinbank_to_outclr_reg, $port:expr, PB) => ($port.outclr1);
+    (pinbank_to_outtgl_reg, $port:expr, PA) => ($port.outtgl0);
+    (pinbank_to_outtgl_reg, $port:expr, PB) => ($port.outtgl1);
     (pinbank_to_dirset_reg, $port:expr, PA) => ($port.dirset0);
     (pinbank_to_dirset_reg, $port:expr, PB) => ($port.dirset1);
     (pinbank_to_dirclr_reg, $port:expr, PA) => ($port.dirclr0);
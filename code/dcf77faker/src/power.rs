@@ -0,0 +1,51 @@
+//! Code to put the microcontroller into standby mode while it has nothing to do.
+//!
+//! In standby mode, the CPU and all clocks and peripherals that are not explicitly configured to
+//! run in standby are stopped. The clocks feeding the carrier (XOSC or DFLL48M, XOSC32K, GCLK0 and
+//! GCLK3) as well as TCC0, TC0 and the RTC are set up to run in standby, so the carrier and its
+//! modulation continue undisturbed while the CPU sleeps. The RTC and TC0 interrupts wake the CPU
+//! up again.
+//!
+//! The SysTick timer is part of the CPU and stops as well; see [`crate::tick::uptime_ms`].
+
+
+use atsaml21g18b::Peripherals;
+use cortex_m::peripheral::SCB;
+
+
+const SLEEPMODE_STANDBY: u8 = 0x4;
+const VREGSMOD_PERFORMANCE: u8 = 0x1;
+
+
+/// Configures the microcontroller to enter standby mode when the CPU waits for an interrupt.
+///
+/// The main voltage regulator is kept running at the current performance level in standby, as the
+/// low-power regulator cannot supply the high-frequency clocks that keep running.
+pub(crate) fn setup_standby(peripherals: &mut Peripherals, scb: &mut SCB) {
+    // keep the main voltage regulator and performance level 2 in standby
+    peripherals.SUPC.vreg.modify(|_, w| w
+        .runstdby().set_bit() // main regulator keeps running in standby
+        .stdbypl0().clear_bit() // don't drop to performance level 0 in standby
+    );
+    peripherals.PM.stdbycfg.modify(|_, w| unsafe { w
+        .vregsmod().bits(VREGSMOD_PERFORMANCE) // regulator in performance mode during standby
+    });
+
+    // select standby as the sleep mode
+    peripherals.PM.sleepcfg.write(|w| unsafe { w
+        .sleepmode().bits(SLEEPMODE_STANDBY)
+    });
+    // the write must have taken effect before going to sleep
+    while peripherals.PM.sleepcfg.read().sleepmode().bits() != SLEEPMODE_STANDBY {
+    }
+
+    scb.set_sleepdeep();
+}
+
+
+/// Puts the CPU to sleep until the next interrupt.
+#[inline]
+pub(crate) fn sleep() {
+    cortex_m::asm::dsb();
+    cortex_m::asm::wfi();
+}
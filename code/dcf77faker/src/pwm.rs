@@ -1,7 +1,94 @@
 //! Code relevant to pulse-width modulation.
 
+use core::fmt;
+use core::time::Duration;
+
 use atsaml21g18b::Peripherals;
 
+use crate::init::CORE_CLOCK_SPEED_HZ;
+use crate::tick::delay;
+
+
+/// The GCLK peripheral channel feeding TCC0 and TCC1, which share a single channel.
+const GCLK_TCC0_THROUGH_TCC1: usize = 25;
+
+/// The largest value that fits into the 24-bit `PER` register.
+const MAX_PERIOD: u32 = 0xFF_FFFF;
+
+
+/// The factor by which a TCC divides its clock before counting.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Prescaler {
+    Div1,
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div64,
+    Div256,
+    Div1024,
+}
+impl Prescaler {
+    /// The factor by which the clock is divided.
+    pub const fn divisor(&self) -> u32 {
+        match self {
+            Self::Div1 => 1,
+            Self::Div2 => 2,
+            Self::Div4 => 4,
+            Self::Div8 => 8,
+            Self::Div16 => 16,
+            Self::Div64 => 64,
+            Self::Div256 => 256,
+            Self::Div1024 => 1024,
+        }
+    }
+
+    /// Decodes the value of the `CTRLA.PRESCALER` field.
+    const fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0 => Self::Div1,
+            1 => Self::Div2,
+            2 => Self::Div4,
+            3 => Self::Div8,
+            4 => Self::Div16,
+            5 => Self::Div64,
+            6 => Self::Div256,
+            _ => Self::Div1024,
+        }
+    }
+}
+
+
+/// A compare channel of a TCC.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Channel {
+    Cc0,
+    Cc1,
+    Cc2,
+    Cc3,
+}
+impl Channel {
+    /// The index of the channel's `CC` register.
+    const fn index(&self) -> usize {
+        match self {
+            Self::Cc0 => 0,
+            Self::Cc1 => 1,
+            Self::Cc2 => 2,
+            Self::Cc3 => 3,
+        }
+    }
+}
+
+
+/// The error returned when a PWM frequency cannot be generated with the current prescaler.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct FrequencyOutOfRangeError;
+impl fmt::Display for FrequencyOutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frequency out of range")
+    }
+}
+
 
 /// PWM functionality implemented using a TCC module.
 pub(crate) trait TccPwm {
@@ -47,7 +134,7 @@ pub(crate) trait TccPwm {
             .msync().clear_bit() // no master synchronization
             .alock().clear_bit() // no auto-lock (= no CTRLB.LUPD changes on overflow/underflow/retrigger)
             .prescsync().presc() // reload/reset counter on tick of prescaled clock
-            .runstdby().set_bit() // run TCC0 in standby
+            .runstdby().set_bit() // run TCC in standby
             .prescaler().div1() // no prescaling (divide by 1)
             .resolution().none() // no dithering
         );
@@ -108,15 +195,99 @@ pub(crate) trait TccPwm {
         );
     }
 
+    /// Sets up dual-slope (center-aligned) PWM.
+    ///
+    /// This is the same as [`setup_pwm`], except that the counter runs in dual-slope mode:
+    ///
+    /// 1. The counter counts up from 0 to `PER`, then back down to 0.
+    ///
+    /// 2. While counting up, the output is set low when the counter reaches `CC0`.
+    ///
+    /// 3. While counting down, the output is set high when the counter reaches `CC0`.
+    ///
+    /// A full cycle therefore takes twice as many counter ticks as in single-slope mode, which
+    /// halves the frequency for a given `PER`; the ratio of `CC0` to `PER` still defines the duty
+    /// cycle. The pulses are centered around the counter reaching 0, which keeps their phase fixed
+    /// when the duty cycle changes. [`set_frequency_hz`] takes the halving into account.
+    ///
+    /// [`setup_pwm`]: TccPwm::setup_pwm
+    /// [`set_frequency_hz`]: TccPwm::set_frequency_hz
+    fn setup_pwm_dual_slope(peripherals: &mut Peripherals) {
+        Self::setup_pwm(peripherals);
+
+        let register_block = Self::get_register_block(peripherals);
+        register_block.wave.modify(|_, w| w
+            .wavegen().dstop() // dual-slope PWM, buffered values are updated at the top
+        );
+        while register_block.syncbusy.read().wave().bit_is_set() {
+        }
+    }
+
+    /// Whether the counter runs in dual-slope mode (see [`setup_pwm_dual_slope`]).
+    ///
+    /// [`setup_pwm_dual_slope`]: TccPwm::setup_pwm_dual_slope
+    fn is_dual_slope(peripherals: &mut Peripherals) -> bool {
+        let register_block = Self::get_register_block(peripherals);
+        // all dual-slope modes have the highest bit of WAVEGEN set
+        register_block.wave.read().wavegen().bits() & 0b100 != 0
+    }
+
+    /// Sets the factor by which the TCC divides the core clock before counting.
+    ///
+    /// [`setup_pwm`] leaves the clock undivided, which is what the carrier needs; slower signals
+    /// (e.g. for a backlight or a buzzer) may need a prescaler to keep the period within the 24 bits
+    /// of `PER`. The prescaler can only be changed while the TCC is disabled, so a running TCC is
+    /// stopped briefly and started again afterwards.
+    ///
+    /// [`setup_pwm`]: TccPwm::setup_pwm
+    fn set_prescaler(peripherals: &mut Peripherals, prescaler: Prescaler) {
+        let register_block = Self::get_register_block(peripherals);
+
+        let was_enabled = register_block.ctrla.read().enable().bit_is_set();
+        if was_enabled {
+            register_block.ctrla.modify(|_, w| w
+                .enable().clear_bit()
+            );
+            while register_block.syncbusy.read().enable().bit_is_set() {
+            }
+        }
+
+        register_block.ctrla.modify(|_, w| match prescaler {
+            Prescaler::Div1 => w.prescaler().div1(),
+            Prescaler::Div2 => w.prescaler().div2(),
+            Prescaler::Div4 => w.prescaler().div4(),
+            Prescaler::Div8 => w.prescaler().div8(),
+            Prescaler::Div16 => w.prescaler().div16(),
+            Prescaler::Div64 => w.prescaler().div64(),
+            Prescaler::Div256 => w.prescaler().div256(),
+            Prescaler::Div1024 => w.prescaler().div1024(),
+        });
+
+        if was_enabled {
+            register_block.ctrla.modify(|_, w| w
+                .enable().set_bit()
+            );
+            while register_block.syncbusy.read().enable().bit_is_set() {
+            }
+        }
+    }
+
+    /// Returns the factor by which the TCC currently divides the core clock before counting.
+    fn prescaler(peripherals: &mut Peripherals) -> Prescaler {
+        let register_block = Self::get_register_block(peripherals);
+        Prescaler::from_bits(register_block.ctrla.read().prescaler().bits())
+    }
+
     /// Sets the period of the PWM generation.
     ///
-    /// The TCC increases the counter on every cycle of the core clock ([`CORE_CLOCK_SPEED_HZ`]).
-    /// The period value defines how many times this has to occur before the counter is reset to 0.
-    /// This defines the frequency of the PWM signal; to define the duty cycle, see
-    /// [`set_duty_cycle`].
+    /// The TCC increases the counter on every cycle of the core clock ([`CORE_CLOCK_SPEED_HZ`]),
+    /// divided by the prescaler (see [`set_prescaler`]). The period value defines how many times
+    /// this has to occur before the counter is reset to 0. This defines the frequency of the PWM
+    /// signal; to define the duty cycle, see [`set_duty_cycle`].
     ///
     /// [`CORE_CLOCK_SPEED_HZ`]: crate::init::CORE_CLOCK_SPEED_HZ
     /// [`set_duty_cycle`]: TccPwm::set_duty_cycle
+    /// [`set_prescaler`]: TccPwm::set_prescaler
     fn set_period(peripherals: &mut Peripherals, period: u32) {
         let register_block = Self::get_register_block(peripherals);
         register_block.per().write(|w| w
@@ -126,6 +297,34 @@ pub(crate) trait TccPwm {
         }
     }
 
+    /// Sets the period of the PWM generation such that the signal has the given frequency.
+    ///
+    /// The period is derived from [`CORE_CLOCK_SPEED_HZ`], the current prescaler (see
+    /// [`set_prescaler`]) and whether the counter runs in dual-slope mode (see
+    /// [`setup_pwm_dual_slope`]), and rounded to the nearest value. Fails without changing the period if
+    /// the frequency is 0, too high to be generated at all, or so low that the period does not fit
+    /// into `PER`; in the latter case, a larger prescaler may help.
+    ///
+    /// [`CORE_CLOCK_SPEED_HZ`]: crate::init::CORE_CLOCK_SPEED_HZ
+    /// [`set_prescaler`]: TccPwm::set_prescaler
+    /// [`setup_pwm_dual_slope`]: TccPwm::setup_pwm_dual_slope
+    fn set_frequency_hz(peripherals: &mut Peripherals, frequency_hz: u32) -> Result<(), FrequencyOutOfRangeError> {
+        if frequency_hz == 0 {
+            return Err(FrequencyOutOfRangeError);
+        }
+        let mut counter_hz = CORE_CLOCK_SPEED_HZ / Self::prescaler(peripherals).divisor();
+        if Self::is_dual_slope(peripherals) {
+            // counting up and back down takes twice as long
+            counter_hz /= 2;
+        }
+        let period = (counter_hz + frequency_hz / 2) / frequency_hz;
+        if period == 0 || period > MAX_PERIOD {
+            return Err(FrequencyOutOfRangeError);
+        }
+        Self::set_period(peripherals, period);
+        Ok(())
+    }
+
     /// Sets the duty cycle of the PWM generation.
     ///
     /// The TCC increases the counter on every cycle of the core clock ([`CORE_CLOCK_SPEED_HZ`]).
@@ -136,11 +335,38 @@ pub(crate) trait TccPwm {
     /// [`CORE_CLOCK_SPEED_HZ`]: crate::init::CORE_CLOCK_SPEED_HZ
     /// [`set_period`]: TccPwm::set_period
     fn set_duty_cycle(peripherals: &mut Peripherals, duty_cycle: u32) {
+        Self::set_channel_duty_cycle(peripherals, Channel::Cc0, duty_cycle);
+    }
+
+    /// Sets the duty cycle of the given channel of the PWM generation.
+    ///
+    /// See [`set_duty_cycle`] for the meaning of the value.
+    ///
+    /// [`set_duty_cycle`]: TccPwm::set_duty_cycle
+    fn set_channel_duty_cycle(peripherals: &mut Peripherals, channel: Channel, duty_cycle: u32) {
         let register_block = Self::get_register_block(peripherals);
-        register_block.cc()[0].write(|w| w
+        register_block.cc()[channel.index()].write(|w| w
             .cc().variant(duty_cycle)
         );
-        while register_block.syncbusy.read().cc0().bit_is_set() {
+        wait_for_cc_sync(register_block, channel);
+    }
+
+    /// Changes the duty cycle of the given channel gradually from its current value to
+    /// `target_duty_cycle`, in `steps` equal steps with `step_delay` between each.
+    ///
+    /// This blocks for `steps` times `step_delay` and relies on the tick clock, so it may only be
+    /// called from the main loop, never from an interrupt handler. If `steps` is 0, the target is
+    /// set immediately.
+    fn fade_to(peripherals: &mut Peripherals, channel: Channel, target_duty_cycle: u32, steps: u16, step_delay: Duration) {
+        let start = Self::duty_cycle(peripherals, channel) as i64;
+        let difference = (target_duty_cycle as i64) - start;
+        for step in 1..=(steps as i64) {
+            let duty_cycle = start + difference * step / (steps as i64);
+            Self::set_channel_duty_cycle(peripherals, channel, duty_cycle as u32);
+            delay(step_delay);
+        }
+        if steps == 0 {
+            Self::set_channel_duty_cycle(peripherals, channel, target_duty_cycle);
         }
     }
 
@@ -172,6 +398,72 @@ pub(crate) trait TccPwm {
         }
     }
 
+    /// Returns the current period of the PWM generation.
+    ///
+    /// Waits until a previously set period has been synchronized, so the returned value is the one
+    /// that is actually in effect. See [`set_period`] for the meaning of the value.
+    ///
+    /// [`set_period`]: TccPwm::set_period
+    fn period(peripherals: &mut Peripherals) -> u32 {
+        let register_block = Self::get_register_block(peripherals);
+        while register_block.syncbusy.read().per().bit_is_set() {
+        }
+        register_block.per().read().per().bits()
+    }
+
+    /// Returns the current duty cycle of the given channel of the PWM generation.
+    ///
+    /// Waits until a previously set duty cycle has been synchronized, so the returned value is the
+    /// one that is actually in effect. See [`set_duty_cycle`] for the meaning of the value.
+    ///
+    /// [`set_duty_cycle`]: TccPwm::set_duty_cycle
+    fn duty_cycle(peripherals: &mut Peripherals, channel: Channel) -> u32 {
+        let register_block = Self::get_register_block(peripherals);
+        wait_for_cc_sync(register_block, channel);
+        register_block.cc()[channel.index()].read().cc().bits()
+    }
+
+    /// Returns the current duty cycle of channel 0, which [`set_duty_cycle`] sets.
+    ///
+    /// [`set_duty_cycle`]: TccPwm::set_duty_cycle
+    fn get_duty_cycle(peripherals: &mut Peripherals) -> u32 {
+        Self::duty_cycle(peripherals, Channel::Cc0)
+    }
+
+    /// Returns the current period of the PWM generation; equivalent to [`period`](TccPwm::period).
+    fn get_period(peripherals: &mut Peripherals) -> u32 {
+        Self::period(peripherals)
+    }
+
+    /// Forces the output of channel 0 low, regardless of the duty cycle.
+    ///
+    /// This uses the pattern generator, which overrides the waveform output; the period and duty
+    /// cycle remain unchanged and take effect again once [`unblank`] is called.
+    ///
+    /// [`unblank`]: TccPwm::unblank
+    fn blank(peripherals: &mut Peripherals) {
+        let register_block = Self::get_register_block(peripherals);
+        register_block.patt.modify(|_, w| w
+            .pgv0().clear_bit() // output low...
+            .pge0().set_bit() // ...instead of the waveform on output channel 0
+        );
+        while register_block.syncbusy.read().patt().bit_is_set() {
+        }
+    }
+
+    /// Lifts the override imposed by [`blank`], returning to the regular PWM output with the
+    /// current duty cycle.
+    ///
+    /// [`blank`]: TccPwm::blank
+    fn unblank(peripherals: &mut Peripherals) {
+        let register_block = Self::get_register_block(peripherals);
+        register_block.patt.modify(|_, w| w
+            .pge0().clear_bit() // output the waveform on output channel 0
+        );
+        while register_block.syncbusy.read().patt().bit_is_set() {
+        }
+    }
+
     /// Starts the timer.
     fn start_generation(peripherals: &mut Peripherals) {
         let register_block = Self::get_register_block(peripherals);
@@ -191,13 +483,78 @@ pub(crate) trait TccPwm {
         while register_block.syncbusy.read().enable().bit_is_set() {
         }
     }
+
+    /// Freezes the counter without disabling the TCC.
+    ///
+    /// Unlike [`stop_generation`], this keeps the counter value; the outputs hold their current
+    /// level until [`resume_generation`] is called.
+    ///
+    /// [`stop_generation`]: TccPwm::stop_generation
+    /// [`resume_generation`]: TccPwm::resume_generation
+    fn pause_generation(peripherals: &mut Peripherals) {
+        let register_block = Self::get_register_block(peripherals);
+        register_block.ctrlbset.modify(|_, w| w
+            .cmd().stop() // freeze the counter
+        );
+        while register_block.syncbusy.read().ctrlb().bit_is_set() {
+        }
+    }
+
+    /// Lets a counter frozen by [`pause_generation`] continue from its current value.
+    ///
+    /// [`pause_generation`]: TccPwm::pause_generation
+    fn resume_generation(peripherals: &mut Peripherals) {
+        let register_block = Self::get_register_block(peripherals);
+        register_block.ctrlbset.modify(|_, w| w
+            .cmd().retrigger() // a stopped counter resumes from its current value
+        );
+        while register_block.syncbusy.read().ctrlb().bit_is_set() {
+        }
+    }
+
+    /// Shifts the phase of the output by lengthening a single period by the given number of counter
+    /// ticks, or shortening it if `ticks` is negative.
+    ///
+    /// The modified period is buffered and takes effect at the end of the current period. This waits
+    /// until it has started (at most one period), then buffers the regular period again, so exactly
+    /// one period is modified. A longer period delays all following edges, i.e. retards the phase; a
+    /// shorter one advances it. The duty cycle is not adjusted for the modified period.
+    fn shift_phase(peripherals: &mut Peripherals, ticks: i32) {
+        let period = Self::period(peripherals);
+        let register_block = Self::get_register_block(peripherals);
+        register_block.perbuf().write(|w| w
+            .perbuf().variant(period.wrapping_add_signed(ticks))
+        );
+
+        // the buffer registers are not synchronized; PERBUFV is set right away and cleared once
+        // PERBUF has been copied into PER at the end of the period
+        while register_block.status.read().perbufv().bit_is_set() {
+        }
+        register_block.perbuf().write(|w| w
+            .perbuf().variant(period)
+        );
+    }
+}
+
+/// Waits until the compare value of the given channel has been synchronized.
+fn wait_for_cc_sync(register_block: &atsaml21g18b::tcc0::RegisterBlock, channel: Channel) {
+    loop {
+        let syncbusy = register_block.syncbusy.read();
+        let busy = match channel {
+            Channel::Cc0 => syncbusy.cc0().bit_is_set(),
+            Channel::Cc1 => syncbusy.cc1().bit_is_set(),
+            Channel::Cc2 => syncbusy.cc2().bit_is_set(),
+            Channel::Cc3 => syncbusy.cc3().bit_is_set(),
+        };
+        if !busy {
+            break;
+        }
+    }
 }
 
 pub(crate) struct Tcc0Pwm;
 impl TccPwm for Tcc0Pwm {
     fn enable_clock(peripherals: &mut Peripherals) {
-        const GCLK_TCC0_THROUGH_TCC1: usize = 25;
-
         peripherals.MCLK.apbcmask.modify(|_, w| w
             .tcc0_().set_bit()
         );
@@ -210,3 +567,21 @@ impl TccPwm for Tcc0Pwm {
         unsafe { &*atsaml21g18b::TCC0::PTR }
     }
 }
+
+pub(crate) struct Tcc1Pwm;
+impl TccPwm for Tcc1Pwm {
+    fn enable_clock(peripherals: &mut Peripherals) {
+        peripherals.MCLK.apbcmask.modify(|_, w| w
+            .tcc1_().set_bit()
+        );
+        // shared with TCC0; enabling it again is harmless
+        peripherals.GCLK.pchctrl[GCLK_TCC0_THROUGH_TCC1].modify(|_, w| w
+            .chen().set_bit()
+        );
+    }
+
+    fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::tcc0::RegisterBlock {
+        // TCC1 has the same register layout as TCC0
+        unsafe { &*atsaml21g18b::TCC1::PTR }
+    }
+}
@@ -16,16 +16,34 @@ pub(crate) fn enable_clock(peripherals: &mut Peripherals) {
 }
 
 
+/// The frequency at which the RTC counter counts, in Hz.
+pub(crate) const COUNTS_PER_SECOND: u32 = 1024;
+
+/// The number of counts between two RTC interrupts (ticks).
+pub(crate) const COUNTS_PER_TICK: u32 = COUNTS_PER_SECOND / (crate::transmitter::TICKS_PER_SECOND as u32);
+
+// the counter wraps around at 2**32, which must be a whole number of ticks
+const _: () = assert!(COUNTS_PER_TICK.is_power_of_two(), "ticks must evenly divide the counter range");
+
+
 /// Sets up RTC.
-pub(crate) fn setup_rtc(peripherals: &mut Peripherals) {
+///
+/// The counter counts freely at [`COUNTS_PER_SECOND`]; an interrupt is raised when it reaches the
+/// value passed to [`set_next_tick`], starting at [`COUNTS_PER_TICK`]. As the counter itself is
+/// never reset, the interrupt handler can always tell from [`count`] how many ticks are due, even
+/// if it has been held up.
+///
+/// `correction` is the frequency correction in counts per 2<sup>20</sup> (about 0.95 ppm each),
+/// between -127 and 127; positive values slow the RTC down, negative values speed it up.
+pub(crate) fn setup_rtc(peripherals: &mut Peripherals, correction: i8) {
     enable_clock(peripherals);
 
     // raw frequency: 32_768 Hz
-    // prescaler: 1/1024
-    // final frequency: 32 Hz
-    // we need to act every second => a 16-bit counter is enough
-    // => use RTC mode 1
-    let register_block = peripherals.RTC.mode1();
+    // prescaler: 1/32
+    // final frequency: 1024 Hz
+    // the counter runs freely and an interrupt is raised every 32 counts (32 Hz) by moving the
+    // compare value along => use RTC mode 0 (32-bit counter with compare)
+    let register_block = peripherals.RTC.mode0();
 
     // reset RTC
     register_block.ctrla.modify(|_, w| w
@@ -36,21 +54,33 @@ pub(crate) fn setup_rtc(peripherals: &mut Peripherals) {
 
     // basic configuration
     register_block.ctrla.modify(|_, w| w
-        .mode().count16() // mode 1 (16-bit counter)
-        .prescaler().div1024() // prescaler to 1/1024
+        .mode().count32() // mode 0 (32-bit counter)
+        .prescaler().div32() // prescaler to 1/32
+        .matchclr().clear_bit() // keep counting on compare match
+        .countsync().set_bit() // allow reading COUNT
         .enable().clear_bit() // don't start yet
     );
+    while register_block.syncbusy.read().countsync().bit_is_set() {
+    }
 
-    // set period to 32
-    register_block.per.modify(|_, w| w
-        .per().variant(32)
+    // first tick
+    register_block.comp[0].write(|w| w
+        .comp().variant(COUNTS_PER_TICK)
     );
-    while register_block.syncbusy.read().per().bit_is_set() {
+    while register_block.syncbusy.read().comp0().bit_is_set() {
     }
 
-    // interrupt on overflow
+    // correct the frequency of the crystal
+    register_block.freqcorr.write(|w| unsafe { w
+        .value().bits(correction.unsigned_abs())
+        .sign().bit(correction < 0) // set = negative correction = faster
+    });
+    while register_block.syncbusy.read().freqcorr().bit_is_set() {
+    }
+
+    // interrupt on compare match
     register_block.intenset.modify(|_, w| w
-        .ovf().set_bit()
+        .cmp0().set_bit()
     );
 
     // start
@@ -62,6 +92,82 @@ pub(crate) fn setup_rtc(peripherals: &mut Peripherals) {
 }
 
 
+/// Returns the current value of the counter, which increases [`COUNTS_PER_SECOND`] times per
+/// second and wraps around.
+pub(crate) fn count(peripherals: &mut Peripherals) -> u32 {
+    let register_block = peripherals.RTC.mode0();
+    while register_block.syncbusy.read().count().bit_is_set() {
+    }
+    register_block.count.read().count().bits()
+}
+
+
+/// Clears the pending tick interrupt.
+pub(crate) fn acknowledge_tick(peripherals: &mut Peripherals) {
+    unsafe {
+        peripherals.RTC.mode0().intflag.write_with_zero(|w| w
+            .cmp0().set_bit()
+        )
+    };
+}
+
+
+/// The frequency of the periodic interrupt (see [`set_periodic_interrupt`]), in Hz.
+///
+/// This is the PER2 output of the prescaler, which divides the 32 768 Hz oscillator by 2<sup>5</sup>.
+pub(crate) const PERIODIC_INTERRUPTS_PER_SECOND: u32 = 1024;
+
+
+/// Enables or disables an additional RTC interrupt at [`PERIODIC_INTERRUPTS_PER_SECOND`].
+///
+/// The periodic interrupt does not affect the ticks; it only serves to wake up the CPU more often
+/// than once per tick.
+pub(crate) fn set_periodic_interrupt(peripherals: &mut Peripherals, enabled: bool) {
+    let register_block = peripherals.RTC.mode0();
+    if enabled {
+        register_block.intenset.modify(|_, w| w
+            .per2().set_bit()
+        );
+    } else {
+        unsafe {
+            register_block.intenclr.write_with_zero(|w| w
+                .per2().set_bit()
+            )
+        };
+    }
+}
+
+
+/// Clears the pending periodic interrupt.
+pub(crate) fn acknowledge_periodic(peripherals: &mut Peripherals) {
+    unsafe {
+        peripherals.RTC.mode0().intflag.write_with_zero(|w| w
+            .per2().set_bit()
+        )
+    };
+}
+
+
+/// Whether the tick interrupt is pending.
+pub(crate) fn tick_pending(peripherals: &mut Peripherals) -> bool {
+    peripherals.RTC.mode0().intflag.read().cmp0().bit_is_set()
+}
+
+
+/// Raises the next tick interrupt when the counter reaches the given value.
+///
+/// If the counter has already passed the value, the interrupt is only raised once the counter
+/// wraps around; check [`count`] afterwards.
+pub(crate) fn set_next_tick(peripherals: &mut Peripherals, tick_count: u32) {
+    let register_block = peripherals.RTC.mode0();
+    register_block.comp[0].write(|w| w
+        .comp().variant(tick_count)
+    );
+    while register_block.syncbusy.read().comp0().bit_is_set() {
+    }
+}
+
+
 /// Enable the RTC interrupt.
 pub(crate) fn enable_interrupt() {
     unsafe {
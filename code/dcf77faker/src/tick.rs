@@ -7,12 +7,41 @@ use crate::init::CORE_CLOCK_SPEED_HZ;
 use crate::sync_vcell::SyncVolatileCell;
 
 
+/// The number of core clock cycles per SysTick interrupt (one millisecond).
+const CYCLES_PER_TICK: u32 = CORE_CLOCK_SPEED_HZ / 1000;
+
+// the SysTick reload value register is 24 bits wide
+const _: () = assert!(CYCLES_PER_TICK - 1 <= 0xFF_FFFF, "core clock too fast for a 1 ms SysTick");
+
+
 pub(crate) static TICK_CLOCK: SyncVolatileCell<u32> = SyncVolatileCell::new(0);
 
+/// The number of times [`TICK_CLOCK`] has wrapped around.
+static TICK_CLOCK_HIGH: SyncVolatileCell<u32> = SyncVolatileCell::new(0);
+
 
 #[exception]
 unsafe fn SysTick() {
-    TICK_CLOCK.set(TICK_CLOCK.get().wrapping_add(1))
+    let ticks = TICK_CLOCK.get().wrapping_add(1);
+    if ticks == 0 {
+        TICK_CLOCK_HIGH.set(TICK_CLOCK_HIGH.get().wrapping_add(1));
+    }
+    TICK_CLOCK.set(ticks)
+}
+
+/// Returns the number of milliseconds since the tick clock has been enabled.
+///
+/// The tick clock is stopped while the CPU is in standby (see [`crate::power`]), so this only
+/// measures the time spent awake.
+pub fn uptime_ms() -> u64 {
+    loop {
+        // if the tick clock wraps around between reading the two halves, try again
+        let high = TICK_CLOCK_HIGH.get();
+        let low = TICK_CLOCK.get();
+        if TICK_CLOCK_HIGH.get() == high {
+            return ((high as u64) << 32) | (low as u64);
+        }
+    }
 }
 
 pub fn enable_tick_clock(core_peripherals: &mut Peripherals) {
@@ -20,8 +49,9 @@ pub fn enable_tick_clock(core_peripherals: &mut Peripherals) {
     const SYST_CSR_TICKINT_ENABLED: u32 = 1 << 1;
     const SYST_CSR_CLKSOURCE_MCK: u32 = 1 << 2;
 
+    // the counter counts from the reload value down to 0 inclusive
     unsafe {
-        core_peripherals.SYST.rvr.write(CORE_CLOCK_SPEED_HZ / 1000)
+        core_peripherals.SYST.rvr.write(CYCLES_PER_TICK - 1)
     };
     unsafe {
         core_peripherals.SYST.csr.write(
@@ -32,17 +62,43 @@ pub fn enable_tick_clock(core_peripherals: &mut Peripherals) {
     };
 }
 
+/// Converts the duration into milliseconds, saturating at `u32::MAX`.
 #[inline]
-pub fn delay(duration: Duration) {
+fn duration_to_ms(duration: Duration) -> u32 {
     let ms_u128 = duration.as_millis();
-    let ms = if ms_u128 > u32::MAX.into() {
+    if ms_u128 > u32::MAX.into() {
         u32::MAX
     } else {
         ms_u128 as u32
-    };
+    }
+}
+
+#[inline]
+pub fn delay(duration: Duration) {
+    let ms = duration_to_ms(duration);
 
     let start = TICK_CLOCK.get();
     while TICK_CLOCK.get() < start + ms {
         // nop
     }
 }
+
+/// Spins until `condition` returns `true` or `timeout` has elapsed.
+///
+/// Returns whether the condition has been met. The condition is always checked at least once,
+/// even if the timeout is zero.
+pub fn delay_until<F: FnMut() -> bool>(timeout: Duration, mut condition: F) -> bool {
+    let ms = duration_to_ms(timeout);
+
+    let start = TICK_CLOCK.get();
+    loop {
+        if condition() {
+            return true;
+        }
+
+        // the first tick might come right after we started; only count full milliseconds
+        if TICK_CLOCK.get().wrapping_sub(start) > ms {
+            return false;
+        }
+    }
+}
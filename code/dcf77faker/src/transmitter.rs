@@ -0,0 +1,558 @@
+//! The state machine that transmits DCF77 frames, independent of the hardware.
+//!
+//! The state machine is driven by a tick 32 times per second (the RTC interrupt on the
+//! microcontroller). Its effects on the carrier and the display are expressed through the
+//! [`CarrierSink`] and [`DisplaySink`] traits, which allow it to be run on the host against mocks
+//! that record what would have been emitted.
+
+
+use core::fmt::{self, Write};
+
+use crate::dcf77::Dcf77Data;
+
+
+/// The number of ticks per second.
+pub const TICKS_PER_SECOND: u8 = 32;
+
+/// The number of ticks for which the amplitude is reduced to transmit a 0 bit.
+///
+/// 3 ticks are 93.75 ms, as close as the tick gets to the nominal 100 ms.
+pub const SHORT_REDUCTION_TICKS: u8 = 3;
+
+/// The number of ticks for which the amplitude is reduced to transmit a 1 bit.
+///
+/// 6 ticks are 187.5 ms, as close as the tick gets to the nominal 200 ms.
+pub const LONG_REDUCTION_TICKS: u8 = 6;
+
+
+/// The amplitude of the carrier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Amplitude {
+    /// Full amplitude, transmitted for the remainder of each second.
+    Full,
+
+    /// Reduced amplitude, transmitted at the start of each second to encode the bit.
+    Reduced,
+
+    /// No carrier at all.
+    ///
+    /// This is not part of the DCF77 signal; in particular, the minute marker is transmitted at
+    /// full amplitude.
+    Off,
+}
+
+
+/// What happens to the time when the seconds are reset, e.g. to synchronize to a reference clock.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SecondReset {
+    /// The current minute starts over at second 0.
+    Zero,
+
+    /// The time is rounded to the nearest minute: from second 30 on, the following minute starts
+    /// at second 0; before that, the current minute starts over.
+    RoundToMinute,
+}
+
+
+/// Something that outputs the carrier, e.g. a PWM peripheral.
+pub trait CarrierSink {
+    /// Changes the amplitude of the carrier.
+    fn set_amplitude(&mut self, amplitude: Amplitude);
+
+    /// Signals the start of a second in which the given bit is transmitted, or `None` during the
+    /// minute marker (the 59th second, or the 60th if a leap second is inserted).
+    fn start_second(&mut self, bit: Option<bool>);
+}
+
+
+/// Something that shows text at a given location, e.g. a character display.
+pub trait DisplaySink {
+    type Error;
+
+    /// Moves to the given column and row.
+    fn set_cursor(&mut self, column: u8, row: u8) -> Result<(), Self::Error>;
+
+    /// Outputs a string at the current location.
+    fn put_str(&mut self, s: &str) -> Result<(), Self::Error>;
+}
+
+
+/// Adapts a [`DisplaySink`] to [`fmt::Write`], storing the error of the sink.
+struct SinkWriter<'d, D: DisplaySink> {
+    sink: &'d mut D,
+    error: Option<D::Error>,
+}
+impl<'d, D: DisplaySink> SinkWriter<'d, D> {
+    fn new(sink: &'d mut D) -> Self {
+        Self { sink, error: None }
+    }
+
+    fn finish(self) -> Result<(), D::Error> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+impl<'d, D: DisplaySink> fmt::Write for SinkWriter<'d, D> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+
+        if let Err(e) = self.sink.put_str(s) {
+            self.error = Some(e);
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+
+/// The column of the colon between the hour and the minute in the output of [`show_time`].
+pub const HOUR_MINUTE_SEPARATOR_COLUMN: u8 = 11;
+
+
+/// Moves to the start of the given row and shows the given date and time.
+pub fn show_time<D: DisplaySink>(display: &mut D, row: u8, data: &Dcf77Data, second: u8) -> Result<(), D::Error> {
+    display.set_cursor(0, row)?;
+
+    let mut writer = SinkWriter::new(display);
+    let _ = write!(
+        writer,
+        "{:02}.{:02}.{:02} {:02}:{:02}:{:02}",
+        data.day_of_month(), data.month(), data.year_in_century(),
+        data.hour(), data.minute(), second,
+    );
+    writer.finish()
+}
+
+
+/// Shows or hides the colon between the hour and the minute that [`show_time`] output on the given
+/// row, e.g. to make it blink.
+pub fn show_hour_minute_separator<D: DisplaySink>(display: &mut D, row: u8, visible: bool) -> Result<(), D::Error> {
+    display.set_cursor(HOUR_MINUTE_SEPARATOR_COLUMN, row)?;
+    display.put_str(if visible { ":" } else { " " })
+}
+
+
+/// Moves to the start of the given row and shows what is currently being transmitted.
+///
+/// The status consists of the current bit (`0`, `1`, or `-` during the minute marker), the time
+/// zone being announced, and `gap` during the minute marker.
+pub fn show_status<D: DisplaySink>(display: &mut D, row: u8, data: &Dcf77Data, bit: Option<bool>) -> Result<(), D::Error> {
+    display.set_cursor(0, row)?;
+
+    let bit_char = match bit {
+        Some(false) => '0',
+        Some(true) => '1',
+        None => '-',
+    };
+    let zone = match (data.cet, data.cest) {
+        (true, false) => "CET",
+        (false, true) => "CEST",
+        _ => "?",
+    };
+    let gap = if bit.is_none() { "gap" } else { "" };
+
+    let mut writer = SinkWriter::new(display);
+    let _ = write!(writer, "bit:{} {:<4} {:<3}", bit_char, zone, gap);
+    writer.finish()
+}
+
+
+/// Whether a leap second is inserted before the given minute.
+///
+/// Leap seconds are inserted at the end of the last minute of a month in UTC, and only if they have
+/// been announced (see [`Dcf77Data::leap_second_announcement`]).
+const fn inserts_leap_second_before(minute: &Dcf77Data) -> bool {
+    minute.leap_second_announcement
+        && minute.day_of_month() == 1
+        && minute.hour() as i8 == minute.utc_offset_hours()
+        && minute.minute() == 0
+}
+
+
+/// The state of the transmission.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Transmitter {
+    /// The current time. The frame being transmitted describes the following minute (see
+    /// [`Dcf77Data::frame_bits`]).
+    data: Dcf77Data,
+
+    /// The current second (0 through 59, or 60 during a leap second).
+    second: u8,
+
+    /// The current tick within the second (0 through `TICKS_PER_SECOND - 1`).
+    tick: u8,
+
+    /// The bits of the current frame that remain to be transmitted, starting with the least
+    /// significant bit.
+    frame: u64,
+
+    /// The tick at which the amplitude returns to full, or 0 if it is not reduced this second.
+    reduction_ticks: u8,
+
+    /// The bit being transmitted during the current second, or `None` during the minute marker.
+    bit: Option<bool>,
+
+    /// Whether a leap second is inserted at the end of the current minute.
+    leap_second: bool,
+}
+impl Transmitter {
+    /// Creates a new transmitter at the end of the given second of the given time.
+    ///
+    /// The following tick starts the next second. If the transmitter starts in the middle of a
+    /// minute (e.g. with a time restored after a reset), the rest of the frame for that minute is
+    /// transmitted right away.
+    pub const fn new(data: Dcf77Data, second: u8) -> Self {
+        let mut next_minute = data;
+        next_minute.increment_minute();
+        let frame = if second < 59 {
+            // the bits up to and including this second have already been transmitted
+            next_minute.to_bits() >> (second + 1)
+        } else {
+            0
+        };
+        Self {
+            data,
+            second,
+            tick: TICKS_PER_SECOND - 1,
+            frame,
+            reduction_ticks: 0,
+            bit: None,
+            leap_second: inserts_leap_second_before(&next_minute),
+        }
+    }
+
+    /// The current time.
+    pub const fn data(&self) -> Dcf77Data {
+        self.data
+    }
+
+    /// Mutable access to the current time; changes take effect with the next frame.
+    pub fn data_mut(&mut self) -> &mut Dcf77Data {
+        &mut self.data
+    }
+
+    /// The current second (0 through 59, or 60 during a leap second).
+    pub const fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// The current tick within the second (0 through `TICKS_PER_SECOND - 1`).
+    pub const fn tick_in_second(&self) -> u8 {
+        self.tick
+    }
+
+    /// The bit being transmitted during the current second, or `None` during the minute marker
+    /// (the gap in the 59th second, or in the 60th if a leap second is inserted).
+    pub const fn transmitted_bit(&self) -> Option<bool> {
+        self.bit
+    }
+
+    /// Resets the seconds according to the given policy, so that the next tick starts second 0.
+    ///
+    /// The frame for the resulting minute is recomputed when second 0 starts. The minute marker of
+    /// the interrupted minute is not transmitted.
+    pub fn reset_second(&mut self, policy: SecondReset) {
+        let next_minute = match policy {
+            SecondReset::Zero => false,
+            SecondReset::RoundToMinute => self.second >= 30,
+        };
+        if !next_minute {
+            // starting second 0 advances the minute; compensate for that
+            self.data.decrement_minute();
+        }
+
+        self.second = 59;
+        self.tick = TICKS_PER_SECOND - 1;
+        self.leap_second = false;
+    }
+
+    /// Advances the state by one tick, modulating the carrier accordingly.
+    ///
+    /// Returns whether a new second has started.
+    pub fn tick<C: CarrierSink>(&mut self, carrier: &mut C) -> bool {
+        self.tick = (self.tick + 1) % TICKS_PER_SECOND;
+
+        if self.tick != 0 {
+            if self.tick == self.reduction_ticks {
+                // end of the amplitude reduction; back to full amplitude for the rest of the second
+                carrier.set_amplitude(Amplitude::Full);
+            }
+            return false;
+        }
+
+        let marker_second = if self.leap_second { 60 } else { 59 };
+        self.second += 1;
+        if self.second > marker_second {
+            self.second = 0;
+        }
+
+        if self.second == marker_second {
+            // the minute marker is the absence of the reduction: the carrier stays at full
+            // amplitude from the end of the reduction in second 58 until second 0 starts; switching
+            // the carrier off instead would look like a long reduction (or a lost signal) to a
+            // receiver, which then fails to find the start of the minute
+            carrier.set_amplitude(Amplitude::Full);
+            self.reduction_ticks = 0;
+            self.bit = None;
+        } else {
+            // regular behavior
+
+            if self.second == 0 {
+                // the minute marker has passed and the previous frame has come into effect;
+                // advance the time and start transmitting the frame for the following minute
+                self.data.increment_minute();
+                if self.leap_second {
+                    // the announced leap second is over
+                    self.data.leap_second_announcement = false;
+                    self.leap_second = false;
+                }
+                let mut next_minute = self.data;
+                next_minute.increment_minute();
+                self.frame = next_minute.to_bits();
+                self.leap_second = inserts_leap_second_before(&next_minute);
+            }
+
+            // lop the last bit off of the frame (the inserted second 59 of a leap second minute
+            // finds the frame empty and transmits a 0 bit)
+            let bit = (self.frame & 0b1) != 0;
+            self.frame >>= 1;
+
+            // reduce the amplitude at the start of the second; the length of the reduction encodes
+            // the bit
+            carrier.set_amplitude(Amplitude::Reduced);
+            self.reduction_ticks = if bit { LONG_REDUCTION_TICKS } else { SHORT_REDUCTION_TICKS };
+            self.bit = Some(bit);
+        }
+        carrier.start_second(self.bit);
+
+        true
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Amplitude, CarrierSink, DisplaySink, LONG_REDUCTION_TICKS, SecondReset, SHORT_REDUCTION_TICKS,
+        TICKS_PER_SECOND, Transmitter, show_hour_minute_separator, show_status, show_time,
+    };
+    use crate::dcf77::Dcf77Data;
+
+    /// Records the envelope of the carrier, one entry per second.
+    struct RecordingCarrier {
+        amplitude: Amplitude,
+        seconds: Vec<(Option<bool>, u8)>,
+    }
+    impl RecordingCarrier {
+        fn new() -> Self {
+            Self { amplitude: Amplitude::Off, seconds: Vec::new() }
+        }
+
+        /// Counts the current tick towards the reduced ticks of the current second.
+        fn record_tick(&mut self) {
+            if self.amplitude == Amplitude::Reduced {
+                self.seconds.last_mut().unwrap().1 += 1;
+            }
+        }
+    }
+    impl CarrierSink for RecordingCarrier {
+        fn set_amplitude(&mut self, amplitude: Amplitude) {
+            self.amplitude = amplitude;
+        }
+
+        fn start_second(&mut self, bit: Option<bool>) {
+            self.seconds.push((bit, 0));
+        }
+    }
+
+    /// Records the text shown on each row.
+    struct RecordingDisplay {
+        rows: [String; 4],
+        row: usize,
+        column: usize,
+    }
+    impl DisplaySink for RecordingDisplay {
+        type Error = ();
+
+        fn set_cursor(&mut self, column: u8, row: u8) -> Result<(), ()> {
+            self.row = row.into();
+            self.column = column.into();
+            if self.column == 0 {
+                self.rows[self.row].clear();
+            }
+            Ok(())
+        }
+
+        fn put_str(&mut self, s: &str) -> Result<(), ()> {
+            let row = &mut self.rows[self.row];
+            let end = (self.column + s.len()).min(row.len());
+            row.replace_range(self.column..end, s);
+            self.column += s.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_full_frame() {
+        let data = Dcf77Data::new();
+        let mut transmitter = Transmitter::new(data, 59);
+        let mut carrier = RecordingCarrier::new();
+        for _ in 0..(60 * TICKS_PER_SECOND as usize) {
+            transmitter.tick(&mut carrier);
+            carrier.record_tick();
+        }
+
+        let mut current = data;
+        current.increment_minute();
+        assert_eq!(transmitter.data(), current);
+        assert_eq!(transmitter.second(), 59);
+
+        let frame = current.frame_bits();
+        assert_eq!(carrier.seconds.len(), 60);
+        for (second, &(bit, reduced_ticks)) in carrier.seconds.iter().enumerate() {
+            if second == 59 {
+                assert_eq!(bit, None);
+                assert_eq!(reduced_ticks, 0);
+            } else {
+                let expected_bit = (frame >> second) & 0b1 != 0;
+                let expected_ticks = if expected_bit { LONG_REDUCTION_TICKS } else { SHORT_REDUCTION_TICKS };
+                assert_eq!(bit, Some(expected_bit), "second {}", second);
+                assert_eq!(reduced_ticks, expected_ticks, "second {}", second);
+            }
+        }
+        assert_eq!(carrier.amplitude, Amplitude::Full);
+    }
+
+    #[test]
+    fn test_leap_second() {
+        // Monday, 2024-07-01, 01:58 CEST = 2024-06-30, 23:58 UTC
+        let mut data = Dcf77Data::from_unix_timestamp(1_719_791_880);
+        data.leap_second_announcement = true;
+        let mut transmitter = Transmitter::new(data, 59);
+        let mut carrier = RecordingCarrier::new();
+        for _ in 0..(61 * TICKS_PER_SECOND as usize) {
+            transmitter.tick(&mut carrier);
+            carrier.record_tick();
+        }
+        assert_eq!((transmitter.data().hour(), transmitter.data().minute()), (1, 59));
+        assert_eq!(transmitter.second(), 60);
+
+        // second 59 transmits a 0 bit, second 60 is the minute marker
+        assert_eq!(carrier.seconds.len(), 61);
+        assert_eq!(carrier.seconds[59], (Some(false), SHORT_REDUCTION_TICKS));
+        assert_eq!(carrier.seconds[60], (None, 0));
+
+        // the following minute is back to 60 seconds and no longer announces a leap second
+        assert!(transmitter.tick(&mut carrier));
+        assert_eq!(transmitter.second(), 0);
+        assert_eq!((transmitter.data().hour(), transmitter.data().minute()), (2, 0));
+        assert!(!transmitter.data().leap_second_announcement);
+        for _ in 0..(59 * TICKS_PER_SECOND as usize) {
+            transmitter.tick(&mut carrier);
+        }
+        assert_eq!((transmitter.second(), transmitter.transmitted_bit()), (59, None));
+    }
+
+    #[test]
+    fn test_leap_second_only_at_end_of_month() {
+        // Monday, 2024-07-15, 01:58 CEST
+        let mut data = Dcf77Data::from_unix_timestamp(1_721_001_480);
+        data.leap_second_announcement = true;
+        let mut transmitter = Transmitter::new(data, 59);
+        let mut carrier = RecordingCarrier::new();
+        for _ in 0..(60 * TICKS_PER_SECOND as usize) {
+            transmitter.tick(&mut carrier);
+        }
+        assert_eq!((transmitter.second(), transmitter.transmitted_bit()), (59, None));
+        assert!(transmitter.tick(&mut carrier));
+        assert_eq!(transmitter.second(), 0);
+        assert!(transmitter.data().leap_second_announcement);
+    }
+
+    #[test]
+    fn test_tick_reports_new_second() {
+        let mut transmitter = Transmitter::new(Dcf77Data::new(), 10);
+        let mut carrier = RecordingCarrier::new();
+        assert!(transmitter.tick(&mut carrier));
+        assert_eq!(transmitter.second(), 11);
+        for _ in 1..TICKS_PER_SECOND {
+            assert!(!transmitter.tick(&mut carrier));
+        }
+        assert!(transmitter.tick(&mut carrier));
+        assert_eq!(transmitter.second(), 12);
+    }
+
+    #[test]
+    fn test_start_mid_minute() {
+        let data = Dcf77Data::new();
+        let frame = data.frame_bits();
+        let mut transmitter = Transmitter::new(data, 12);
+        let mut carrier = RecordingCarrier::new();
+        for _ in 0..(47 * TICKS_PER_SECOND as usize) {
+            transmitter.tick(&mut carrier);
+            carrier.record_tick();
+        }
+        assert_eq!(transmitter.second(), 59);
+        assert_eq!(transmitter.data(), data);
+
+        // seconds 13 through 58 carry the rest of the frame, including the start of time (bit 20)
+        assert_eq!(carrier.seconds.len(), 47);
+        assert_eq!(carrier.seconds[20 - 13], (Some(true), LONG_REDUCTION_TICKS));
+        for (i, &(bit, _)) in carrier.seconds[..46].iter().enumerate() {
+            let second = 13 + i;
+            assert_eq!(bit, Some((frame >> second) & 0b1 != 0), "second {}", second);
+        }
+        assert_eq!(carrier.seconds[46], (None, 0));
+    }
+
+    #[test]
+    fn test_reset_second() {
+        let data = Dcf77Data::new();
+        let mut next_minute = data;
+        next_minute.increment_minute();
+        let mut carrier = RecordingCarrier::new();
+
+        for (second, policy, expected) in [
+            (12, SecondReset::Zero, data),
+            (45, SecondReset::Zero, data),
+            (12, SecondReset::RoundToMinute, data),
+            (29, SecondReset::RoundToMinute, data),
+            (30, SecondReset::RoundToMinute, next_minute),
+            (45, SecondReset::RoundToMinute, next_minute),
+        ] {
+            // press the button during the given second
+            let mut transmitter = Transmitter::new(data, second - 1);
+            transmitter.tick(&mut carrier);
+            transmitter.tick(&mut carrier);
+            assert_eq!(transmitter.second(), second);
+            transmitter.reset_second(policy);
+
+            assert!(transmitter.tick(&mut carrier));
+            assert_eq!(transmitter.second(), 0, "{:?} at {}", policy, second);
+            assert_eq!(transmitter.data(), expected, "{:?} at {}", policy, second);
+            assert_eq!(transmitter.transmitted_bit(), Some(false));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let mut display = RecordingDisplay { rows: Default::default(), row: 0, column: 0 };
+        let data = Dcf77Data::new();
+        show_time(&mut display, 2, &data, 7).unwrap();
+        show_status(&mut display, 1, &data, Some(true)).unwrap();
+        assert_eq!(display.rows[2], "10.04.90 10:40:07");
+        assert_eq!(display.rows[1], "bit:1 CEST    ");
+
+        show_status(&mut display, 1, &data, None).unwrap();
+        assert_eq!(display.rows[1], "bit:- CEST gap");
+
+        show_hour_minute_separator(&mut display, 2, false).unwrap();
+        assert_eq!(display.rows[2], "10.04.90 10 40:07");
+        show_hour_minute_separator(&mut display, 2, true).unwrap();
+        assert_eq!(display.rows[2], "10.04.90 10:40:07");
+    }
+}
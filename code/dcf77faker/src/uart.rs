@@ -0,0 +1,160 @@
+//! Code to send debug output via a UART.
+
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use atsaml21g18b::Peripherals;
+
+use crate::init::CORE_CLOCK_SPEED_HZ;
+
+
+/// UART speed in bits per second.
+const UART_SPEED_BAUD: u32 = 115_200;
+
+
+const fn calculate_baud_value() -> u16 {
+    // asynchronous arithmetic mode with 16x oversampling:
+    // f_BAUD = f_REF / 16 * (1 - BAUD / 65536)
+
+    // f_BAUD * 16 / f_REF = 1 - BAUD / 65536
+    // BAUD / 65536 = 1 - f_BAUD * 16 / f_REF
+    // BAUD = 65536 * (1 - f_BAUD * 16 / f_REF)
+    // BAUD = 65536 - 65536 * 16 * f_BAUD / f_REF
+
+    (65536 - (65536 * 16 * (UART_SPEED_BAUD as u64)) / (CORE_CLOCK_SPEED_HZ as u64)) as u16
+}
+
+
+/// A SERCOM device that can act as a UART.
+///
+/// The UART is configured for 8 data bits, no parity and 1 stop bit (8N1), with TX on PAD[0] and
+/// RX on PAD[1].
+pub(crate) trait SercomUart {
+    /// Unmasks the clock signals going to the SERCOM device.
+    fn enable_clock(peripherals: &mut Peripherals);
+
+    /// Obtains a reference to the SERCOM register block.
+    fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::sercom0::USART;
+
+    /// Sets up the SERCOM device as a UART.
+    fn setup_uart(peripherals: &mut Peripherals) {
+        Self::enable_clock(peripherals);
+
+        let register_block = Self::get_register_block(peripherals);
+
+        // reset SERCOM
+        register_block.ctrla.modify(|_, w| w
+            .swrst().set_bit()
+        );
+        while register_block.ctrla.read().swrst().bit_is_set() || register_block.syncbusy.read().swrst().bit_is_set() {
+        }
+
+        // basic configuration
+        register_block.ctrla.modify(|_, w| w
+            .mode().variant(0x1) // USART with internal clock
+            .cmode().clear_bit() // asynchronous communication
+            .sampr().variant(0) // 16x oversampling, arithmetic baud rate generation
+            .txpo().variant(0) // TX on PAD[0]
+            .rxpo().variant(1) // RX on PAD[1]
+            .form().variant(0) // USART frame without parity
+            .dord().set_bit() // LSB first
+            .runstdby().clear_bit() // no need to run in standby
+        );
+        register_block.ctrlb.modify(|_, w| w
+            .chsize().variant(0) // 8 bits per character
+            .sbmode().clear_bit() // 1 stop bit
+            .txen().set_bit() // enable transmitter
+            .rxen().set_bit() // enable receiver
+        );
+        while register_block.syncbusy.read().ctrlb().bit_is_set() {
+        }
+        register_block.baud().modify(|_, w| w
+            .baud().variant(calculate_baud_value())
+        );
+
+        // enable UART
+        register_block.ctrla.modify(|_, w| w
+            .enable().set_bit()
+        );
+        while register_block.syncbusy.read().enable().bit_is_set() {
+        }
+    }
+
+    /// Sends bytes via the UART, blocking until the last byte has been transmitted completely.
+    fn write_bytes<I: IntoIterator<Item = u8>>(peripherals: &mut Peripherals, bytes: I) {
+        let register_block = Self::get_register_block(peripherals);
+
+        for byte in bytes {
+            // wait until the data register is empty
+            while register_block.intflag.read().dre().bit_is_clear() {
+            }
+            register_block.data.write(|w| w
+                .data().variant(byte.into())
+            );
+        }
+
+        // wait until the transmission is complete, then clear that bit
+        while register_block.intflag.read().txc().bit_is_clear() {
+        }
+        unsafe {
+            register_block.intflag.write_with_zero(|w| w
+                .txc().set_bit()
+            )
+        };
+    }
+
+    /// Receives a byte via the UART, blocking until one arrives.
+    fn read_byte(peripherals: &mut Peripherals) -> u8 {
+        let register_block = Self::get_register_block(peripherals);
+
+        // wait until a byte has been received (reading it clears that bit)
+        while register_block.intflag.read().rxc().bit_is_clear() {
+        }
+        (register_block.data.read().data().bits() & 0xFF) as u8
+    }
+}
+
+
+/// Allows formatted output to a UART using [`write!`] and [`writeln!`].
+pub(crate) struct UartWriter<'p, U: SercomUart> {
+    peripherals: &'p mut Peripherals,
+    uart: PhantomData<U>,
+}
+impl<'p, U: SercomUart> UartWriter<'p, U> {
+    pub fn new(peripherals: &'p mut Peripherals) -> Self {
+        Self {
+            peripherals,
+            uart: PhantomData,
+        }
+    }
+}
+impl<'p, U: SercomUart> fmt::Write for UartWriter<'p, U> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        U::write_bytes(self.peripherals, s.bytes());
+        Ok(())
+    }
+}
+
+
+pub(crate) struct Sercom3Uart;
+impl SercomUart for Sercom3Uart {
+    fn enable_clock(peripherals: &mut Peripherals) {
+        const GCLK_SERCOM3_CORE: usize = 21;
+        const GCLK_SERCOM0_THROUGH_SERCOM4_SLOW: usize = 17;
+
+        peripherals.MCLK.apbcmask.modify(|_, w| w
+            .sercom3_().set_bit()
+        );
+        peripherals.GCLK.pchctrl[GCLK_SERCOM3_CORE].modify(|_, w| w
+            .chen().set_bit()
+        );
+        peripherals.GCLK.pchctrl[GCLK_SERCOM0_THROUGH_SERCOM4_SLOW].modify(|_, w| w
+            .chen().set_bit()
+        );
+    }
+
+    fn get_register_block(peripherals: &mut Peripherals) -> &atsaml21g18b::sercom0::USART {
+        unsafe { (&*atsaml21g18b::SERCOM3::PTR).usart() }
+    }
+}
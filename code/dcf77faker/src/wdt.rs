@@ -0,0 +1,97 @@
+//! Code to control the watchdog timer.
+//!
+//! On the SAM L21, the watchdog timer is always clocked by the 1.024 kHz output of the internal
+//! ultra-low-power 32 kHz oscillator (`OSCULP32K`), which keeps running even if the external
+//! oscillators fail.
+
+
+use atsaml21g18b::Peripherals;
+
+
+/// The key that must be written to the `CLEAR` register to reset the watchdog timer.
+const CLEAR_KEY: u8 = 0xA5;
+
+
+/// The number of watchdog clock cycles (1.024 kHz) after which the watchdog resets the
+/// microcontroller unless it has been fed.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum WatchdogPeriod {
+    Cycles8,
+    Cycles16,
+    Cycles32,
+    Cycles64,
+    Cycles128,
+    Cycles256,
+    Cycles512,
+    Cycles1024,
+    Cycles2048,
+    Cycles4096,
+    Cycles8192,
+    Cycles16384,
+}
+impl WatchdogPeriod {
+    /// Converts this period to the representation in the configuration register.
+    #[inline]
+    pub const fn to_nibble(&self) -> u8 {
+        match self {
+            Self::Cycles8 => 0x0,
+            Self::Cycles16 => 0x1,
+            Self::Cycles32 => 0x2,
+            Self::Cycles64 => 0x3,
+            Self::Cycles128 => 0x4,
+            Self::Cycles256 => 0x5,
+            Self::Cycles512 => 0x6,
+            Self::Cycles1024 => 0x7,
+            Self::Cycles2048 => 0x8,
+            Self::Cycles4096 => 0x9,
+            Self::Cycles8192 => 0xA,
+            Self::Cycles16384 => 0xB,
+        }
+    }
+}
+
+
+/// Sets up the watchdog timer with the given period, or disables it if `period` is `None`.
+///
+/// Once enabled, the watchdog must be fed regularly using [`feed`].
+pub(crate) fn setup_watchdog(peripherals: &mut Peripherals, period: Option<WatchdogPeriod>) {
+    let wdt = &peripherals.WDT;
+
+    // the configuration can only be changed while the watchdog is disabled
+    wdt.ctrla.modify(|_, w| w
+        .enable().clear_bit()
+    );
+    while wdt.syncbusy.read().enable().bit_is_set() {
+    }
+
+    let period = match period {
+        Some(p) => p,
+        None => return,
+    };
+
+    wdt.config.modify(|_, w| unsafe { w
+        .per().bits(period.to_nibble())
+    });
+
+    wdt.ctrla.modify(|_, w| w
+        .wen().clear_bit() // normal mode, not window mode
+        .enable().set_bit()
+    );
+    while wdt.syncbusy.read().enable().bit_is_set() {
+    }
+}
+
+
+/// Feeds the watchdog, restarting its timeout period.
+///
+/// Returns immediately if the previous feeding is still being synchronized, as the watchdog is then
+/// being restarted anyway.
+pub(crate) fn feed(peripherals: &mut Peripherals) {
+    let wdt = &peripherals.WDT;
+    if wdt.syncbusy.read().clear().bit_is_set() {
+        return;
+    }
+    wdt.clear.write(|w| unsafe { w
+        .clear().bits(CLEAR_KEY)
+    });
+}